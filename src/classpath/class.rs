@@ -0,0 +1,226 @@
+/// A field or method of a Java class, as declared in its class file.
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: u16,
+    /// The value of a constant field, from its `ConstantValue` attribute.
+    pub constant_value: Option<ConstantValue>,
+}
+
+/// The value of a field initialized with a compile-time constant.
+#[derive(Debug, Clone)]
+pub enum ConstantValue {
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    String(String),
+}
+
+impl Member {
+    pub fn is_public(&self) -> bool {
+        self.access_flags & 1 != 0
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.access_flags & 8 != 0
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.access_flags & 0x10 != 0
+    }
+}
+
+/// The signatures of a Java class, read back from its class file.
+#[derive(Debug)]
+pub struct Class {
+    pub name: String,
+    pub access_flags: u16,
+    /// The superclass, absent only for `java/lang/Object`.
+    pub super_class: Option<String>,
+    pub interfaces: Vec<String>,
+    pub fields: Vec<Member>,
+    pub methods: Vec<Member>,
+}
+
+enum Constant {
+    UTF8(String),
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    Class(u16),
+    String(u16),
+    Other,
+}
+
+/// Reads big-endian values, failing at the end of the data.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n { return None; }
+        let (bytes, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+}
+
+impl Class {
+    /// Parses a class file, the inverse of `ClassFile::compile`, returning `None` if it is malformed.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let mut r = Reader(data);
+        if r.u32()? != 0xCAFEBABE { return None; }
+        r.u32()?; // version
+
+        // Indices start at 1, and longs and doubles take up two entries
+        let count = r.u16()?;
+        let mut pool = vec![Constant::Other];
+        while pool.len() < count as usize {
+            let tag = r.u8()?;
+            let constant = match tag {
+                1 => {
+                    let len = r.u16()?;
+                    Constant::UTF8(decode_modified_utf8(r.bytes(len as usize)?))
+                },
+                3 => Constant::Integer(r.u32()? as i32),
+                4 => Constant::Float(f32::from_bits(r.u32()?)),
+                5 => Constant::Long(u64::from_be_bytes(r.bytes(8)?.try_into().unwrap()) as i64),
+                6 => Constant::Double(f64::from_bits(u64::from_be_bytes(r.bytes(8)?.try_into().unwrap()))),
+                7 => Constant::Class(r.u16()?),
+                8 => Constant::String(r.u16()?),
+                16 | 19 | 20 => {
+                    r.u16()?;
+                    Constant::Other
+                },
+                15 => {
+                    r.bytes(3)?;
+                    Constant::Other
+                },
+                9 | 10 | 11 | 12 | 17 | 18 => {
+                    r.u32()?;
+                    Constant::Other
+                },
+                _ => return None,
+            };
+            pool.push(constant);
+            if matches!(tag, 5 | 6) {
+                pool.push(Constant::Other);
+            }
+        }
+
+        let class = |idx: u16| match pool.get(idx as usize) {
+            Some(Constant::Class(idx)) => utf8(&pool, *idx),
+            _ => None,
+        };
+
+        let access_flags = r.u16()?;
+        let name = class(r.u16()?)?;
+        let super_class = match r.u16()? {
+            0 => None,
+            idx => Some(class(idx)?),
+        };
+        let interface_count = r.u16()?;
+        let mut interfaces = vec![];
+        for _ in 0..interface_count {
+            interfaces.push(class(r.u16()?)?);
+        }
+
+        let field_count = r.u16()?;
+        let mut fields = vec![];
+        for _ in 0..field_count {
+            fields.push(parse_member(&mut r, &pool)?);
+        }
+
+        let method_count = r.u16()?;
+        let mut methods = vec![];
+        for _ in 0..method_count {
+            methods.push(parse_member(&mut r, &pool)?);
+        }
+
+        Some(Self { name, access_flags, super_class, interfaces, fields, methods })
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.access_flags & 0x200 != 0
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.access_flags & 0x400 != 0
+    }
+
+    /// The field called `name`.
+    pub fn field(&self, name: &str) -> Option<&Member> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// The methods called `name`.
+    pub fn methods<'b>(&'b self, name: &'b str) -> impl Iterator<Item = &'b Member> {
+        self.methods.iter().filter(move |method| method.name == name)
+    }
+}
+
+fn utf8(pool: &[Constant], idx: u16) -> Option<String> {
+    match pool.get(idx as usize) {
+        Some(Constant::UTF8(value)) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn parse_member(r: &mut Reader, pool: &[Constant]) -> Option<Member> {
+    let access_flags = r.u16()?;
+    let name = utf8(pool, r.u16()?)?;
+    let descriptor = utf8(pool, r.u16()?)?;
+
+    let mut constant_value = None;
+    let attribute_count = r.u16()?;
+    for _ in 0..attribute_count {
+        let attribute = utf8(pool, r.u16()?)?;
+        let len = r.u32()?;
+        let mut data = Reader(r.bytes(len as usize)?);
+        if attribute == "ConstantValue" {
+            constant_value = Some(match pool.get(data.u16()? as usize)? {
+                Constant::Integer(value) => ConstantValue::Integer(*value),
+                Constant::Float(value) => ConstantValue::Float(*value),
+                Constant::Long(value) => ConstantValue::Long(*value),
+                Constant::Double(value) => ConstantValue::Double(*value),
+                Constant::String(idx) => ConstantValue::String(utf8(pool, *idx)?),
+                _ => return None,
+            });
+        }
+    }
+
+    Some(Member { name, descriptor, access_flags, constant_value })
+}
+
+/// Decodes the JVM's modified UTF-8, replacing unpaired surrogates.
+fn decode_modified_utf8(bytes: &[u8]) -> String {
+    let mut units = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i] as u16;
+        let (unit, len) = match b {
+            0x00..=0x7f => (b, 1),
+            0xc0..=0xdf if i + 1 < bytes.len() => (((b & 0x1f) << 6) | (bytes[i + 1] as u16 & 0x3f), 2),
+            0xe0..=0xef if i + 2 < bytes.len() => (((b & 0x0f) << 12) | ((bytes[i + 1] as u16 & 0x3f) << 6) | (bytes[i + 2] as u16 & 0x3f), 3),
+            _ => (0xfffd, 1),
+        };
+        units.push(unit);
+        i += len;
+    }
+
+    char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+}
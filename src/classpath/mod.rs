@@ -0,0 +1,149 @@
+mod class;
+
+pub use class::{Class, ConstantValue, Member};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use zip::ZipArchive;
+
+/// The Java release whose API is available. Compiled classes target Java 8, which is described
+/// by the entries of `ct.sym` whose release directory contains this character.
+const RELEASE: char = '8';
+
+/// A location searched for class files.
+#[derive(Debug)]
+enum Source {
+    /// A directory, holding `java/lang/Math.class` for `java/lang/Math`.
+    Directory(PathBuf),
+    /// A `.jar` or `.zip` archive, laid out like a directory.
+    Archive(RefCell<ZipArchive<File>>),
+}
+
+/// The Java classes available to the compiled code, looked up by internal name.
+#[derive(Debug)]
+pub struct ClassPath {
+    /// The JDK's `lib/ct.sym`, which holds the signatures of its classes for each release.
+    jdk: Option<RefCell<ZipArchive<File>>>,
+    /// The `ct.sym` entry of each class of the release.
+    jdk_entries: HashMap<String, String>,
+    sources: Vec<Source>,
+    classes: RefCell<HashMap<String, Option<Rc<Class>>>>,
+}
+
+impl ClassPath {
+    /// Opens the classes of the JDK found in `JAVA_HOME`, or else through the `java` executable,
+    /// followed by those of `paths`, each a directory or an archive. Fails with a message if one of
+    /// them is neither.
+    pub fn new(paths: &[PathBuf]) -> Result<Self, String> {
+        let mut jdk_entries = HashMap::new();
+        let jdk = java_home()
+            .and_then(|home| File::open(home.join("lib").join("ct.sym")).ok())
+            .and_then(|file| ZipArchive::new(file).ok());
+
+        if let Some(jdk) = &jdk {
+            // Entries are named `{releases}/{module}/{package}/{class}.sig`
+            for entry in jdk.file_names() {
+                let mut parts = entry.splitn(3, '/');
+                let (Some(releases), Some(_), Some(name)) = (parts.next(), parts.next(), parts.next()) else { continue };
+                if let (true, Some(name)) = (releases.contains(RELEASE), name.strip_suffix(".sig")) {
+                    jdk_entries.insert(name.to_string(), entry.to_string());
+                }
+            }
+        }
+
+        let mut sources = vec![];
+        for path in paths {
+            if path.is_dir() {
+                sources.push(Source::Directory(path.clone()));
+                continue;
+            }
+
+            let archive = File::open(path).map_err(|e| e.to_string())
+                .and_then(|file| ZipArchive::new(file).map_err(|e| e.to_string()))
+                .map_err(|e| format!("cannot read class path entry {}: {}", path.display(), e))?;
+            sources.push(Source::Archive(RefCell::new(archive)));
+        }
+
+        Ok(Self {
+            jdk: jdk.map(RefCell::new),
+            jdk_entries,
+            sources,
+            classes: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Finds the class with the internal name `name`, such as `java/lang/Math`.
+    pub fn find(&self, name: &str) -> Option<Rc<Class>> {
+        if let Some(class) = self.classes.borrow().get(name) {
+            return class.clone();
+        }
+
+        // Like `java`, the JDK's classes take precedence over those of the class path
+        let data = self.read_jdk(name)
+            .or_else(|| self.sources.iter().find_map(|source| source.read(name)));
+        let class = data.and_then(|data| Class::parse(&data)).map(Rc::new);
+        self.classes.borrow_mut().insert(name.to_string(), class.clone());
+        class
+    }
+
+    /// The class `name` followed by all the classes and interfaces it extends or implements,
+    /// directly or not, each listed once.
+    pub fn supertypes(&self, name: &str) -> Vec<Rc<Class>> {
+        let mut supertypes: Vec<Rc<Class>> = self.find(name).into_iter().collect();
+        let mut i = 0;
+        while let Some(class) = supertypes.get(i).cloned() {
+            for parent in class.super_class.iter().chain(&class.interfaces) {
+                if supertypes.iter().all(|class| &class.name != parent) {
+                    supertypes.extend(self.find(parent));
+                }
+            }
+            i += 1;
+        }
+        supertypes
+    }
+
+    /// Whether the class `from` is `to`, or extends or implements it, directly or not.
+    pub fn is_subclass(&self, from: &str, to: &str) -> bool {
+        from == to || self.supertypes(from).iter().any(|class| class.name == to)
+    }
+
+    fn read_jdk(&self, name: &str) -> Option<Vec<u8>> {
+        let entry = self.jdk_entries.get(name)?;
+        read_entry(&mut self.jdk.as_ref()?.borrow_mut(), entry)
+    }
+}
+
+impl Source {
+    fn read(&self, name: &str) -> Option<Vec<u8>> {
+        let path = format!("{}.class", name);
+        match self {
+            Source::Directory(dir) => std::fs::read(dir.join(path)).ok(),
+            Source::Archive(archive) => read_entry(&mut archive.borrow_mut(), &path),
+        }
+    }
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, entry: &str) -> Option<Vec<u8>> {
+    let mut file = archive.by_name(entry).ok()?;
+
+    let mut data = vec![];
+    file.read_to_end(&mut data).ok()?;
+    Some(data)
+}
+
+fn java_home() -> Option<PathBuf> {
+    if let Some(home) = std::env::var_os("JAVA_HOME") {
+        return Some(PathBuf::from(home));
+    }
+
+    // `java` is usually a link to `{home}/bin/java`
+    let path = std::env::var_os("PATH")?;
+    let java = std::env::split_paths(&path).map(|dir| dir.join("java")).find(|java| java.is_file())?;
+    let java = std::fs::canonicalize(java).ok()?;
+    Some(java.parent()?.parent()?.to_path_buf())
+}
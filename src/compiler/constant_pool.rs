@@ -8,6 +8,11 @@ pub enum Ref { Field, Method, InterfaceMethod }
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub enum Constant {
     UTF8(String),
+    Integer(i32),
+    /// Floats are stored as their bits, so that constants can be hashed.
+    Float(u32),
+    Long(i64),
+    Double(u64),
     Class(u16),
     String(u16),
     NameAndType(u16, u16),
@@ -32,9 +37,14 @@ impl ConstantPool {
         if let Some(res) = self.pool.get(&constant) {
             *res
         } else {
-            self.count += 1;
-            self.pool.insert(constant, self.count);
-            self.count
+            let idx = self.count + 1;
+            // Longs and doubles take up two entries
+            self.count += match constant {
+                Constant::Long(_) | Constant::Double(_) => 2,
+                _ => 1,
+            };
+            self.pool.insert(constant, idx);
+            idx
         }
     }
 
@@ -42,6 +52,22 @@ impl ConstantPool {
         self.get_or_insert(Constant::UTF8(value))
     }
 
+    pub fn insert_integer(&mut self, value: i32) -> u16 {
+        self.get_or_insert(Constant::Integer(value))
+    }
+
+    pub fn insert_float(&mut self, value: f32) -> u16 {
+        self.get_or_insert(Constant::Float(value.to_bits()))
+    }
+
+    pub fn insert_long(&mut self, value: i64) -> u16 {
+        self.get_or_insert(Constant::Long(value))
+    }
+
+    pub fn insert_double(&mut self, value: f64) -> u16 {
+        self.get_or_insert(Constant::Double(value.to_bits()))
+    }
+
     pub fn insert_class(&mut self, class: String) -> u16 {
         let idx = self.insert_utf8(class);
         self.get_or_insert(Constant::Class(idx))
@@ -73,9 +99,26 @@ impl ConstantPool {
         for (item, _) in items {
             match item {
                 Constant::UTF8(value) => {
+                    let bytes = modified_utf8(value);
                     buf.put_u8(1);
-                    buf.put_u16(value.len() as u16);
-                    buf.put_slice(value.as_bytes());
+                    buf.put_u16(bytes.len() as u16);
+                    buf.put_slice(&bytes);
+                },
+                Constant::Integer(value) => {
+                    buf.put_u8(3);
+                    buf.put_i32(*value);
+                },
+                Constant::Float(bits) => {
+                    buf.put_u8(4);
+                    buf.put_u32(*bits);
+                },
+                Constant::Long(value) => {
+                    buf.put_u8(5);
+                    buf.put_i64(*value);
+                },
+                Constant::Double(bits) => {
+                    buf.put_u8(6);
+                    buf.put_u64(*bits);
                 },
                 Constant::Class(class) => {
                     buf.put_u8(7);
@@ -104,3 +147,24 @@ impl ConstantPool {
         buf.to_vec()
     }
 }
+
+/// Encodes `value` in the JVM's modified UTF-8, where NUL takes two bytes and supplementary
+/// characters are encoded as their UTF-16 surrogates.
+pub fn modified_utf8(value: &str) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(value.len());
+    for unit in value.encode_utf16() {
+        match unit {
+            0x01..=0x7f => buf.push(unit as u8),
+            0x00 | 0x80..=0x7ff => {
+                buf.push(0xc0 | (unit >> 6) as u8);
+                buf.push(0x80 | (unit & 0x3f) as u8);
+            },
+            _ => {
+                buf.push(0xe0 | (unit >> 12) as u8);
+                buf.push(0x80 | ((unit >> 6) & 0x3f) as u8);
+                buf.push(0x80 | (unit & 0x3f) as u8);
+            },
+        }
+    }
+    buf
+}
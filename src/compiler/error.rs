@@ -5,6 +5,27 @@ pub enum CompileErrorId {
     SymbolNotFound(String),
     UnexpectedArgCount(u16),
     UnexpectedArgType(String, String),
+    MismatchedTypes(String, String),
+    UnsupportedOperand(String, String),
+    UnexpectedType(String, String),
+    OutsideLoop(String),
+    ImmutableAssign(String),
+    InvalidAssignTarget,
+    UnknownField(String, String),
+    DuplicateField(String),
+    DuplicateMember(String),
+    MissingField(String),
+    MethodTooLarge(String, String),
+    LiteralOutOfRange(String, String),
+    InvalidCast(String, String),
+    MissingReturn(String),
+    InvalidEscape(String),
+    StringTooLong(usize),
+    NoApplicableMethod(String, String),
+    AbstractInstantiation(String),
+    JumpTooFar(String),
+    AmbiguousCall(String, String),
+    UnreachableCode,
 }
 
 #[derive(Debug)]
@@ -26,7 +47,7 @@ impl CompileError {
         println!("{fileame} {} {}:{}", "-->".blue(), self.start.0, self.start.1);
         println!("{bar}");
 
-        let line = source.split("\n").collect::<Vec<_>>()[self.start.0-1];
+        let line = source.split('\n').collect::<Vec<_>>()[self.start.0-1];
         let offset = line.char_indices().find_map(|(i, c)| if c == ' ' { None } else { Some(i) }).unwrap_or(0);
 
         let line = &line[offset..];
@@ -42,6 +63,27 @@ impl CompileError {
             SymbolNotFound(symbol) => format!("symbol not found: {}", symbol),
             UnexpectedArgCount(expected) => format!("unexpected argument: expected {} arguments", expected),
             UnexpectedArgType(expected, got) => format!("unexpected argument: expected a {}, got a {}", expected, got),
+            MismatchedTypes(lhs, rhs) => format!("mismatched types: {} and {}", lhs, rhs),
+            UnsupportedOperand(op, t) => format!("cannot apply operator {} to a {}", op, t),
+            UnexpectedType(expected, got) => format!("unexpected type: expected a {}, got a {}", expected, got),
+            OutsideLoop(keyword) => format!("{} outside of a loop", keyword),
+            ImmutableAssign(ident) => format!("cannot assign to immutable variable {}", ident),
+            InvalidAssignTarget => "invalid left-hand side of assignment".to_string(),
+            UnknownField(t, field) => format!("no field {} on type {}", field, t),
+            DuplicateField(field) => format!("field {} is initialized more than once", field),
+            DuplicateMember(member) => format!("member {} is declared more than once", member),
+            MissingField(field) => format!("missing field {} in initializer", field),
+            MethodTooLarge(method, what) => format!("method {} exceeds the limit of 65535 {}", method, what),
+            LiteralOutOfRange(lit, t) => format!("literal out of range: {} does not fit in a {}", lit, t),
+            InvalidCast(from, to) => format!("cannot cast a {} to a {}", from, to),
+            MissingReturn(method) => format!("not all paths of {} return a value", method),
+            InvalidEscape(text) => format!("invalid escape in literal: {}", text),
+            StringTooLong(len) => format!("string takes {} bytes, more than the limit of 65535", len),
+            NoApplicableMethod(method, args) => format!("no overload of {} takes arguments ({})", method, args),
+            UnreachableCode => "unreachable code".to_string(),
+            AmbiguousCall(method, args) => format!("call to {} with arguments ({}) is ambiguous", method, args),
+            JumpTooFar(method) => format!("method {} has a jump spanning more than 32767 bytes of code", method),
+            AbstractInstantiation(class) => format!("cannot create an instance of abstract class {}", class),
         };
 
         println!("{} {}: {msg}", "=".blue(), "error".red());
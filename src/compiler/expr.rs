@@ -0,0 +1,150 @@
+use std::sync::OnceLock;
+
+use crate::Rule;
+use super::t::TypeId;
+
+use pest::iterators::{Pair, Pairs};
+use pest::pratt_parser::{Assoc, Op, PrattParser};
+
+fn pratt() -> &'static PrattParser<Rule> {
+    static PRATT: OnceLock<PrattParser<Rule>> = OnceLock::new();
+    PRATT.get_or_init(|| {
+        PrattParser::new()
+            .op(Op::infix(Rule::or, Assoc::Left))
+            .op(Op::infix(Rule::and, Assoc::Left))
+            .op(Op::infix(Rule::eq, Assoc::Left) | Op::infix(Rule::ne, Assoc::Left) | Op::infix(Rule::lt, Assoc::Left) | Op::infix(Rule::le, Assoc::Left) | Op::infix(Rule::gt, Assoc::Left) | Op::infix(Rule::ge, Assoc::Left))
+            .op(Op::infix(Rule::add, Assoc::Left) | Op::infix(Rule::sub, Assoc::Left))
+            .op(Op::infix(Rule::mul, Assoc::Left) | Op::infix(Rule::div, Assoc::Left) | Op::infix(Rule::rem, Assoc::Left))
+            .op(Op::postfix(Rule::cast))
+            .op(Op::prefix(Rule::neg) | Op::prefix(Rule::not))
+            .op(Op::postfix(Rule::field) | Op::postfix(Rule::methodCall) | Op::postfix(Rule::index))
+    })
+}
+
+/// An `expr` node, with operators resolved to a tree by precedence.
+#[derive(Debug)]
+pub enum Expr<'a> {
+    Value(Pair<'a, Rule>),
+    Unary(Pair<'a, Rule>, Box<Expr<'a>>),
+    Postfix(Box<Expr<'a>>, Pair<'a, Rule>),
+    Binary(Box<Expr<'a>>, Pair<'a, Rule>, Box<Expr<'a>>),
+}
+
+impl<'a> Expr<'a> {
+    pub fn parse(pairs: Pairs<'a, Rule>) -> Self {
+        pratt()
+            .map_primary(|primary| match primary.as_rule() {
+                Rule::expr => Self::parse(primary.into_inner()),
+                _ => Self::Value(primary),
+            })
+            .map_prefix(|op, rhs| Self::Unary(op, Box::new(rhs)))
+            .map_postfix(|lhs, op| Self::Postfix(Box::new(lhs), op))
+            .map_infix(|lhs, op, rhs| Self::Binary(Box::new(lhs), op, Box::new(rhs)))
+            .parse(pairs)
+    }
+
+    pub fn line_col(&self) -> (usize, usize) {
+        match self {
+            Self::Value(value) => value.line_col(),
+            Self::Unary(op, _) => op.line_col(),
+            Self::Postfix(lhs, _) | Self::Binary(lhs, _, _) => lhs.line_col(),
+        }
+    }
+
+    /// The value of an `i32` literal, possibly negated.
+    pub fn const_int(&self) -> Option<i64> {
+        match self {
+            Self::Value(value) if value.as_rule() == Rule::numLit => match int_lit(value) {
+                (Some(n), TypeId::I32) => i64::try_from(n).ok(),
+                _ => None,
+            },
+            Self::Unary(op, rhs) if op.as_rule() == Rule::neg => rhs.const_int().map(|n| -n),
+            _ => None,
+        }
+    }
+
+    /// Whether the expression is a comparison or a logical operation, which compiles to jumps.
+    pub fn is_condition(&self) -> bool {
+        match self {
+            Self::Value(_) | Self::Postfix(_, _) => false,
+            Self::Unary(op, _) => op.as_rule() == Rule::not,
+            Self::Binary(_, op, _) => matches!(op.as_rule(), Rule::or | Rule::and | Rule::eq | Rule::ne | Rule::lt | Rule::le | Rule::gt | Rule::ge),
+        }
+    }
+}
+
+/// The type of a `numLit`, given by its suffix, or else `f64` with a fractional part and `i32` without.
+pub fn num_lit_type(pair: &Pair<Rule>) -> TypeId {
+    let mut pairs = pair.clone().into_inner();
+    let digits = pairs.next().unwrap();
+    match pairs.next() {
+        Some(suffix) => suffix.as_str().parse().unwrap(),
+        None if digits.as_rule() == Rule::floatDigits => TypeId::F64,
+        None => TypeId::I32,
+    }
+}
+
+/// Decodes an integer `numLit` into its value, if it fits an `i128`, and its type.
+pub fn int_lit(pair: &Pair<Rule>) -> (Option<i128>, TypeId) {
+    let digits = pair.clone().into_inner().next().unwrap();
+    let t = num_lit_type(pair);
+
+    let text = digits.as_str().replace('_', "");
+    let value = match digits.as_rule() {
+        Rule::hexDigits => i128::from_str_radix(&text[2..], 16),
+        Rule::binDigits => i128::from_str_radix(&text[2..], 2),
+        _ => text.parse(),
+    };
+
+    (value.ok(), t)
+}
+
+/// Decodes a `numLit` with a floating point type into its value. `f32` literals are rounded
+/// straight to an `f32`, as rounding to an `f64` first could give a different value.
+pub fn float_lit(pair: &Pair<Rule>) -> f64 {
+    let digits = pair.clone().into_inner().next().unwrap();
+    let single = num_lit_type(pair) == TypeId::F32;
+    match digits.as_rule() {
+        Rule::floatDigits | Rule::decDigits => {
+            let text = digits.as_str().replace('_', "");
+            if single { text.parse::<f32>().unwrap() as f64 } else { text.parse().unwrap() }
+        },
+        _ => {
+            let value = int_lit(pair).0.unwrap_or(i128::MAX);
+            if single { value as f32 as f64 } else { value as f64 }
+        },
+    }
+}
+
+/// Decodes the escape sequences of the text of a string literal, or returns `None` if a `\u`
+/// escape leaves a surrogate unpaired.
+pub fn unescape(text: &str) -> Option<String> {
+    char::decode_utf16(unescape_utf16(text)).collect::<Result<_, _>>().ok()
+}
+
+/// Decodes the escape sequences of the text of a literal into UTF-16 code units.
+pub fn unescape_utf16(text: &str) -> Vec<u16> {
+    let mut units = vec![];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next().unwrap() {
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'u' => {
+                    let hex = chars.by_ref().take(4).collect::<String>();
+                    units.push(u16::from_str_radix(&hex, 16).unwrap());
+                    continue;
+                },
+                c => c,
+            },
+            c => c,
+        };
+        units.extend(c.encode_utf16(&mut [0; 2]).iter());
+    }
+
+    units
+}
@@ -0,0 +1,99 @@
+use bytes::{BufMut, BytesMut};
+
+use super::constant_pool::ConstantPool;
+use super::t::{Type, TypeId};
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum VerificationType {
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Object(String),
+    Uninitialized(u16),
+}
+
+impl VerificationType {
+    /// The verification type of a value of type `t`, or `None` for `void`.
+    pub fn from_type(t: &Type) -> Option<Self> {
+        use TypeId::*;
+
+        if t.is_array() { return Some(Self::Object(t.class_name())) }
+        Some(match t.id {
+            I8 | I16 | I32 | Char | Bool => Self::Integer,
+            I64 => Self::Long,
+            F32 => Self::Float,
+            F64 => Self::Double,
+            Void => return None,
+            Other(_) => Self::Object(t.class_name()),
+        })
+    }
+
+    /// Number of local variable slots (or stack words) taken by the value.
+    pub fn size(&self) -> u16 {
+        match self {
+            Self::Long | Self::Double => 2,
+            _ => 1,
+        }
+    }
+
+    fn serialize(&self, cp: &mut ConstantPool, buf: &mut BytesMut) {
+        match self {
+            Self::Top => buf.put_u8(0),
+            Self::Integer => buf.put_u8(1),
+            Self::Float => buf.put_u8(2),
+            Self::Long => buf.put_u8(4),
+            Self::Double => buf.put_u8(3),
+            Self::Object(class) => {
+                buf.put_u8(7);
+                buf.put_u16(cp.insert_class(class.to_string()));
+            },
+            Self::Uninitialized(offset) => {
+                buf.put_u8(8);
+                buf.put_u16(*offset);
+            },
+        }
+    }
+}
+
+/// The state of the locals and operand stack at the start of an instruction.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub locals: Vec<VerificationType>,
+    pub stack: Vec<VerificationType>,
+}
+
+impl Frame {
+    pub fn new(locals: Vec<VerificationType>, stack: Vec<VerificationType>) -> Self {
+        Self { locals, stack }
+    }
+}
+
+/// Serializes the body of a `StackMapTable` attribute, every entry being a `full_frame`.
+/// `frames` must be sorted by offset.
+pub fn serialize_stack_map_table(frames: &[(u16, Frame)], cp: &mut ConstantPool) -> Vec<u8> {
+    let mut buf = BytesMut::new();
+    buf.put_u16(frames.len() as u16);
+
+    let mut previous: Option<u16> = None;
+    for (offset, frame) in frames {
+        buf.put_u8(255); // full_frame
+        buf.put_u16(match previous {
+            Some(previous) => offset - previous - 1,
+            None => *offset,
+        });
+        previous = Some(*offset);
+
+        buf.put_u16(frame.locals.len() as u16);
+        for local in &frame.locals {
+            local.serialize(cp, &mut buf);
+        }
+        buf.put_u16(frame.stack.len() as u16);
+        for item in &frame.stack {
+            item.serialize(cp, &mut buf);
+        }
+    }
+
+    buf.to_vec()
+}
@@ -1,20 +1,52 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::{Rule, compiler::t::TypeId};
 use super::t::{Type, Descriptor};
+use super::expr::Expr;
+use super::frame::{self, Frame, VerificationType};
 
 use bytes::{BufMut, BytesMut};
 use pest::iterators::{Pair, Pairs};
 
+struct Label {
+    locals: Vec<VerificationType>,
+    offset: Option<u16>,
+    stack: Option<Vec<VerificationType>>,
+}
+
+/// An enclosing loop, targeted by `break` and `continue`.
+struct Loop<'a> {
+    label: Option<&'a str>,
+    break_label: usize,
+    continue_label: usize,
+}
+
 pub struct MethodCompiler<'a> {
     cp: &'a mut super::constant_pool::ConstantPool,
     name: &'a str,
     descriptor: Descriptor,
+    instance: bool,
 
-    args: HashMap<&'a str, (Type, u8)>,
-    vars: HashMap<&'a str, (Type, u8)>,
+    args: HashMap<&'a str, Local>,
+    vars: Vec<HashMap<&'a str, Local>>,
+    /// First free local slot, and its value when each scope of `vars` was entered.
+    next_slot: u32,
+    scope_slots: Vec<u32>,
+    max_locals: u32,
+    max_stack: u32,
     pub errs: Vec<super::CompileError>,
     b: BytesMut,
+
+    stack: Vec<VerificationType>,
+    labels: Vec<Label>,
+    jumps: Vec<(usize, usize)>,
+    frames: BTreeMap<u16, Frame>,
+    reachable: bool,
+    /// Whether the statements being compiled follow a return or a jump, which was reported.
+    dead_code: bool,
+    loops: Vec<Loop<'a>>,
+    /// Types of the arguments of the Java calls already resolved, by position of the call.
+    call_arg_types: HashMap<usize, Vec<Type>>,
 }
 
 impl<'a> MethodCompiler<'a> {
@@ -23,43 +55,80 @@ impl<'a> MethodCompiler<'a> {
             cp,
             name: method.name,
             descriptor: method.descriptor.clone(),
+            instance: method.instance,
             args: method.args.clone(),
-            vars: HashMap::new(),
+            vars: vec![HashMap::new()],
+            next_slot: method.args.values().map(|local| (local.idx + local.t.size()) as u32).max().unwrap_or(0),
+            scope_slots: vec![],
+            max_locals: method.args.values().map(|local| (local.idx + local.t.size()) as u32).max().unwrap_or(0),
+            max_stack: 0,
             errs: vec![],
             b: BytesMut::new(),
+            stack: vec![],
+            labels: vec![],
+            jumps: vec![],
+            frames: BTreeMap::new(),
+            reachable: true,
+            dead_code: false,
+            loops: vec![],
+            call_arg_types: HashMap::new(),
         }
     }
 
     pub fn compile(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Vec<u8> {
-        let pairs = pair.into_inner();
+        let loc = pair.line_col();
+        let end_loc = pair.as_span().end_pos().line_col();
+        for pair in pair.into_inner() {
+            self.compile_stmt(pair, class);
+        }
 
-        let mut returned = false;
-        for pair in pairs {
-            match pair.as_rule() {
-                Rule::returnStmt => {
-                    match self.compile_value(pair.into_inner().next().unwrap(), class).unwrap().id {
-                        TypeId::I8 | TypeId::I16 | TypeId::I32 | TypeId::I64 | TypeId::Char | TypeId::Bool => self.b.put_u8(172), // ireturn
-                        _ => self.b.put_u8(176), // areturn
-                    };
-                    returned = true;
-                },
-                Rule::callExpr => self.compile_call_expr(pair, class),
-                Rule::varDecl => self.compile_var_decl(pair, class),
-                _ => {
-                    println!("{pair:?}");
-                },
+        if self.reachable {
+            if self.descriptor.return_type.id == TypeId::Void {
+                self.b.put_u8(177); // return
+            } else {
+                self.errs.push(super::CompileError::new(super::CompileErrorId::MissingReturn(self.name.to_string()), end_loc));
             }
         }
 
-        if !returned { self.b.put_u8(177); }
+        for (size, what) in [(self.b.len() as u32, "bytes of code"), (self.max_locals, "local slots"), (self.max_stack, "stack words")] {
+            if size > u16::MAX as u32 {
+                self.errs.push(super::CompileError::new(super::CompileErrorId::MethodTooLarge(self.name.to_string(), what.to_string()), loc));
+            }
+        }
+
+        let mut too_far = false;
+        for (pos, label) in &self.jumps {
+            if let Some(target) = self.labels[*label].offset {
+                // Branch offsets are signed 16-bit values
+                match i16::try_from(target as i32 - *pos as i32) {
+                    Ok(offset) => self.b[pos+1..pos+3].copy_from_slice(&offset.to_be_bytes()),
+                    Err(_) => too_far = true,
+                }
+            }
+        }
+        if too_far {
+            self.errs.push(super::CompileError::new(super::CompileErrorId::JumpTooFar(self.name.to_string()), loc));
+        }
+
+        let mut attributes = bytes::BytesMut::new();
+        let mut attribute_cnt = 0;
+        if !self.frames.is_empty() {
+            let frames = std::mem::take(&mut self.frames).into_iter().collect::<Vec<_>>();
+            let table = frame::serialize_stack_map_table(&frames, self.cp);
+            attributes.put_u16(self.cp.insert_utf8("StackMapTable".to_string()));
+            attributes.put_u32(table.len() as u32);
+            attributes.put_slice(&table);
+            attribute_cnt += 1;
+        }
 
         let mut body = bytes::BytesMut::new();
-        body.put_u16(16);
-        body.put_u16(16);
+        body.put_u16(self.max_stack as u16);
+        body.put_u16(self.max_locals as u16);
         body.put_u32(self.b.len() as u32);
         body.put_slice(&self.b);
         body.put_u16(0);
-        body.put_u16(0);
+        body.put_u16(attribute_cnt);
+        body.put_slice(&attributes);
 
         let mut buf = bytes::BytesMut::new();
         buf.put_u16(self.cp.insert_utf8("Code".to_string()));
@@ -67,7 +136,7 @@ impl<'a> MethodCompiler<'a> {
         buf.put_slice(&body);
 
         let mut res = bytes::BytesMut::new();
-        res.put_u16(1 | 8);
+        res.put_u16(if self.instance { 1 } else { 1 | 8 });
         res.put_u16(self.cp.insert_utf8(self.name.to_string()));
         res.put_u16(self.cp.insert_utf8(self.descriptor.to_string()));
 
@@ -77,65 +146,1291 @@ impl<'a> MethodCompiler<'a> {
         res.to_vec()
     }
 
-    fn compile_call_expr(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) {
+    fn compile_stmt(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) {
+        // Code following a return or a jump is never executed, and the verifier would reject it
+        // without a frame. It is still checked, as if it could be reached, and stays unreachable.
+        if !self.reachable {
+            if !self.dead_code {
+                self.errs.push(super::CompileError::new(super::CompileErrorId::UnreachableCode, pair.line_col()));
+            }
+            self.reachable = true;
+            self.compile_stmt(pair, class);
+            self.reachable = false;
+            self.dead_code = true;
+            return;
+        }
+        self.dead_code = false;
+        self.stack.clear();
+
+        match pair.as_rule() {
+            // The value of the last expression of a function is returned
+            Rule::returnStmt | Rule::tailExpr => {
+                if let Err(e) = self.compile_return(pair, class) {
+                    self.errs.push(e);
+                }
+                self.reachable = false;
+            },
+            Rule::expr => match self.compile_value(pair, class) {
+                Ok(t) => match t.size() {
+                    0 => {},
+                    1 => {
+                        self.b.put_u8(87); // pop
+                        self.pop(1);
+                    },
+                    _ => {
+                        self.b.put_u8(88); // pop2
+                        self.pop(1);
+                    },
+                },
+                Err(e) => self.errs.push(e),
+            },
+            Rule::varDecl => self.compile_var_decl(pair, class),
+            Rule::assignStmt => self.compile_assign_stmt(pair, class),
+            Rule::ifStmt => self.compile_if_stmt(pair, class),
+            Rule::whileStmt | Rule::loopStmt => self.compile_loop(pair, class),
+            Rule::forStmt => self.compile_for(pair, class),
+            Rule::breakStmt | Rule::continueStmt => self.compile_jump_stmt(pair),
+            _ => {
+                println!("{pair:?}");
+            },
+        }
+    }
+
+    /// Compiles a `return` statement or a tail expression, whose value must convert to the method's
+    /// return type.
+    fn compile_return(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<(), super::CompileError> {
+        let loc = pair.line_col();
+        let return_type = self.descriptor.return_type.clone();
+
+        match pair.into_inner().find(|pair| pair.as_rule() != Rule::returnKeyword) {
+            Some(value) => {
+                let value_loc = value.line_col();
+                let t = self.compile_value(value, class)?;
+                if !self.widen(&t, &return_type, class) {
+                    return Err(super::CompileError::new(super::CompileErrorId::UnexpectedType(return_type.to_string(), t.to_string()), value_loc));
+                }
+                match return_type.id {
+                    TypeId::Void => self.b.put_u8(177), // return
+                    _ => {
+                        self.b.put_u8(172 + return_type.opcode_offset()); // *return
+                        self.pop(1);
+                    },
+                }
+            },
+            None if return_type.id == TypeId::Void => self.b.put_u8(177), // return
+            None => return Err(super::CompileError::new(super::CompileErrorId::UnexpectedType(return_type.to_string(), Type::new(TypeId::Void, 0).to_string()), loc)),
+        }
+
+        Ok(())
+    }
+
+    fn compile_block(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) {
+        self.push_scope();
+        for pair in pair.into_inner() {
+            self.compile_stmt(pair, class);
+        }
+        self.pop_scope();
+    }
+
+    fn push_scope(&mut self) {
+        self.vars.push(HashMap::new());
+        self.scope_slots.push(self.next_slot);
+    }
+
+    /// Leaves the innermost scope, whose slots get reused by the following variables.
+    fn pop_scope(&mut self) {
+        self.vars.pop();
+        self.next_slot = self.scope_slots.pop().unwrap();
+    }
+
+    fn compile_if_stmt(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) {
         let mut pairs = pair.into_inner();
+        let cond = pairs.next().unwrap();
+        let then = pairs.next().unwrap();
+
+        let otherwise_label = self.new_label();
+        if let Err(e) = self.compile_cond(Expr::parse(cond.into_inner()), false, otherwise_label, class) {
+            self.errs.push(e);
+        }
+        self.compile_block(then, class);
+
+        match pairs.next() {
+            None => self.bind(otherwise_label),
+            Some(otherwise) => {
+                let end_label = self.new_label();
+                if self.reachable { self.jump(167, end_label); } // goto
+                self.bind(otherwise_label);
+                match otherwise.as_rule() {
+                    Rule::ifStmt => self.compile_if_stmt(otherwise, class),
+                    _ => self.compile_block(otherwise, class),
+                }
+                self.bind(end_label);
+            },
+        }
+    }
+
+    /// Compiles `while` and `loop` statements, the latter having no condition.
+    fn compile_loop(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) {
+        let is_while = pair.as_rule() == Rule::whileStmt;
+        let mut pairs = pair.into_inner();
+        let label = match pairs.peek().unwrap().as_rule() {
+            Rule::loopLabel => Some(pairs.next().unwrap().as_str()),
+            _ => None,
+        };
+
+        let loop_label = self.new_label();
+        let end_label = self.new_label();
+        self.bind(loop_label);
+
+        if is_while {
+            let cond = pairs.next().unwrap();
+            if let Err(e) = self.compile_cond(Expr::parse(cond.into_inner()), false, end_label, class) {
+                self.errs.push(e);
+            }
+        }
+
+        self.loops.push(Loop { label, break_label: end_label, continue_label: loop_label });
+        self.compile_block(pairs.next().unwrap(), class);
+        self.loops.pop();
+
+        if self.reachable { self.jump(167, loop_label); } // goto
+        self.bind(end_label);
+    }
 
+    fn compile_for(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) {
+        let mut pairs = pair.into_inner();
+        let label = match pairs.peek().unwrap().as_rule() {
+            Rule::loopLabel => Some(pairs.next().unwrap().as_str()),
+            _ => None,
+        };
         let ident = pairs.next().unwrap().as_str();
+        let start = pairs.next().unwrap();
+        let start_loc = start.line_col();
+        let end = pairs.next().unwrap();
+        let end_loc = end.line_col();
 
-        match ident {
-            "print" => {
-                self.b.put_u8(178); // getstatic
-                self.b.put_u16(self.cp.insert_ref(crate::compiler::constant_pool::Ref::Field, "java/lang/System".to_string(), "out".to_string(), "Ljava/io/PrintStream;".to_string()));
-                let descriptor = self.compile_args(pairs, class);
-                self.b.put_u8(182); // invokevirtual
-                self.b.put_u16(self.cp.insert_ref(crate::compiler::constant_pool::Ref::Method, "java/io/PrintStream".to_string(), "println".to_string(), descriptor.to_string()));
+        let start_t = match self.compile_value(start, class) {
+            Ok(t) => t,
+            Err(e) => return self.errs.push(e),
+        };
+        if start_t.is_array() || !matches!(start_t.id, TypeId::I8 | TypeId::I16 | TypeId::I32 | TypeId::I64) {
+            return self.errs.push(super::CompileError::new(super::CompileErrorId::UnexpectedType(Type::new(TypeId::I32, 0).to_string(), start_t.to_string()), start_loc));
+        }
+        match self.compile_value(end, class) {
+            Ok(t) if t == start_t => {},
+            Ok(t) => return self.errs.push(super::CompileError::new(super::CompileErrorId::MismatchedTypes(start_t.to_string(), t.to_string()), end_loc)),
+            Err(e) => return self.errs.push(e),
+        }
+
+        let end_label = self.new_label();
+
+        // The bound is evaluated once, into a variable that cannot be named from the source
+        self.push_scope();
+        let end_idx = self.declare("..", start_t.clone(), false);
+        self.store(&start_t, end_idx);
+        let idx = self.declare(ident, start_t.clone(), false);
+        self.store(&start_t, idx);
+
+        let loop_label = self.new_label();
+        let continue_label = self.new_label();
+        self.bind(loop_label);
+
+        self.load(&start_t, idx);
+        self.load(&start_t, end_idx);
+        if start_t.id == TypeId::I64 {
+            self.b.put_u8(148); // lcmp
+            self.pop(2);
+            self.jump(156, end_label); // ifge
+        } else {
+            self.pop(2);
+            self.jump(162, end_label); // if_icmpge
+        }
+
+        self.loops.push(Loop { label, break_label: end_label, continue_label });
+        self.compile_block(pairs.next().unwrap(), class);
+        self.loops.pop();
+
+        self.bind(continue_label);
+        if self.reachable {
+            if start_t.id == TypeId::I64 {
+                self.load(&start_t, idx);
+                self.b.put_u8(10); // lconst_1
+                self.push(&start_t);
+                self.b.put_u8(97); // ladd
+                self.pop(1);
+                self.store(&start_t, idx);
+            } else {
+                self.iinc(idx, 1);
+            }
+            self.jump(167, loop_label); // goto
+        }
+        self.pop_scope();
+
+        self.bind(end_label);
+    }
+
+    fn compile_jump_stmt(&mut self, pair: Pair<'a, Rule>) {
+        let is_break = pair.as_rule() == Rule::breakStmt;
+        let loc = pair.line_col();
+        let label = pair.into_inner().find(|pair| pair.as_rule() == Rule::loopLabel);
+
+        let target = match &label {
+            Some(label) => self.loops.iter().rev().find(|l| l.label == Some(label.as_str())),
+            None => self.loops.last(),
+        };
+        match target {
+            Some(target) => {
+                let target = if is_break { target.break_label } else { target.continue_label };
+                self.jump(167, target); // goto
             },
-            f => {
-                self.compile_args(pairs, class);
-                self.b.put_u8(184); // invokestatic
-                let method = class.methods.get(f).unwrap();
-                self.b.put_u16(self.cp.insert_ref(crate::compiler::constant_pool::Ref::Method, class.this_class.clone(), f.to_string(), method.descriptor.to_string()));
+            None => match label {
+                Some(label) => self.errs.push(super::CompileError::new(super::CompileErrorId::SymbolNotFound(label.as_str().to_string()), label.line_col())),
+                None => self.errs.push(super::CompileError::new(super::CompileErrorId::OutsideLoop(if is_break { "break" } else { "continue" }.to_string()), loc)),
+            },
+        }
+    }
+
+    /// Binds `ident` in the innermost scope, returning its slot.
+    fn declare(&mut self, ident: &'a str, t: Type, mutable: bool) -> u16 {
+        let idx = self.next_slot as u16;
+        self.next_slot += t.size() as u32;
+        self.max_locals = self.max_locals.max(self.next_slot);
+        self.vars.last_mut().unwrap().insert(ident, Local { t, idx, mutable });
+        idx
+    }
+
+    fn load(&mut self, t: &Type, idx: u16) {
+        self.put_local_insn(21 + t.opcode_offset(), idx); // *load
+        self.push(t);
+    }
+
+    fn store(&mut self, t: &Type, idx: u16) {
+        self.put_local_insn(54 + t.opcode_offset(), idx); // *store
+        self.pop(1);
+    }
+
+    /// Emits a `*load` or `*store` instruction, using its `*_n` form when there is one.
+    fn put_local_insn(&mut self, opcode: u8, idx: u16) {
+        match idx {
+            0..=3 if opcode < 54 => self.b.put_u8(26 + (opcode - 21) * 4 + idx as u8), // *load_n
+            0..=3 => self.b.put_u8(59 + (opcode - 54) * 4 + idx as u8), // *store_n
+            4..=255 => {
+                self.b.put_u8(opcode);
+                self.b.put_u8(idx as u8);
+            },
+            _ => {
+                self.b.put_u8(196); // wide
+                self.b.put_u8(opcode);
+                self.b.put_u16(idx);
+            },
+        }
+    }
+
+    fn iinc(&mut self, idx: u16, delta: i16) {
+        match (u8::try_from(idx), i8::try_from(delta)) {
+            (Ok(idx), Ok(delta)) => {
+                self.b.put_u8(132); // iinc
+                self.b.put_u8(idx);
+                self.b.put_i8(delta);
+            },
+            _ => {
+                self.b.put_u8(196); // wide
+                self.b.put_u8(132); // iinc
+                self.b.put_u16(idx);
+                self.b.put_i16(delta);
+            },
+        }
+    }
+
+    fn lookup(&self, ident: &str) -> Option<&Local> {
+        self.vars.iter().rev().find_map(|scope| scope.get(ident)).or_else(|| self.args.get(ident))
+    }
+
+    fn push(&mut self, t: &Type) {
+        if let Some(t) = VerificationType::from_type(t) {
+            self.push_verification_type(t);
+        }
+    }
+
+    fn push_verification_type(&mut self, t: VerificationType) {
+        self.stack.push(t);
+        let depth = self.stack.iter().map(|t| t.size() as u32).sum();
+        self.max_stack = self.max_stack.max(depth);
+    }
+
+    fn pop(&mut self, n: usize) {
+        self.stack.truncate(self.stack.len().saturating_sub(n));
+    }
+
+    /// The verification types of the variables in scope, indexed by slot.
+    fn locals(&self) -> Vec<VerificationType> {
+        let mut slots = self.args.values()
+            .chain(self.vars.iter().flat_map(|scope| scope.values()))
+            .filter_map(|local| Some((local.idx, VerificationType::from_type(&local.t)?)))
+            .collect::<Vec<_>>();
+        slots.sort_by_key(|(idx, _)| *idx);
+
+        let mut locals = vec![];
+        let mut next = 0;
+        for (idx, t) in slots {
+            if idx < next { continue; }
+            while next < idx {
+                locals.push(VerificationType::Top);
+                next += 1;
+            }
+            next += t.size();
+            locals.push(t);
+        }
+
+        locals
+    }
+
+    /// Creates a jump target, which sees the variables currently in scope.
+    fn new_label(&mut self) -> usize {
+        self.labels.push(Label { locals: self.locals(), offset: None, stack: None });
+        self.labels.len() - 1
+    }
+
+    /// Emits the branch instruction `opcode` to `label`, whose operands must already be popped.
+    fn jump(&mut self, opcode: u8, label: usize) {
+        let target = &mut self.labels[label];
+        if target.stack.is_none() {
+            target.stack = Some(self.stack.clone());
+        }
+
+        self.jumps.push((self.b.len(), label));
+        self.b.put_u8(opcode);
+        self.b.put_u16(0);
+
+        if opcode == 167 { self.reachable = false; } // goto
+    }
+
+    /// Places `label` at the current offset, recording a stack map frame there.
+    fn bind(&mut self, label: usize) {
+        let offset = self.b.len() as u16;
+        let target = &mut self.labels[label];
+        target.offset = Some(offset);
+
+        let stack = match &target.stack {
+            Some(stack) => stack.clone(),
+            None if self.reachable => {
+                target.stack = Some(self.stack.clone());
+                self.stack.clone()
+            },
+            None => return,
+        };
+
+        let frame = Frame::new(target.locals.clone(), stack.clone());
+        match self.frames.get(&offset) {
+            Some(existing) if existing.locals.len() <= frame.locals.len() => {},
+            _ => { self.frames.insert(offset, frame); },
+        }
+
+        self.stack = stack;
+        self.reachable = true;
+    }
+
+    /// Compiles a boolean expression as a jump to `label`, taken when the expression evaluates to `jump_if`.
+    fn compile_cond(&mut self, expr: Expr<'a>, jump_if: bool, label: usize, class: &super::ClassFile<'a>) -> Result<(), super::CompileError> {
+        match expr {
+            Expr::Unary(op, rhs) if op.as_rule() == Rule::not => self.compile_cond(*rhs, !jump_if, label, class),
+            Expr::Binary(lhs, op, rhs) if matches!(op.as_rule(), Rule::and | Rule::or) => {
+                if (op.as_rule() == Rule::and) != jump_if {
+                    // Either operand alone is enough to take the jump
+                    self.compile_cond(*lhs, jump_if, label, class)?;
+                    self.compile_cond(*rhs, jump_if, label, class)
+                } else {
+                    let skip_label = self.new_label();
+                    self.compile_cond(*lhs, !jump_if, skip_label, class)?;
+                    self.compile_cond(*rhs, jump_if, label, class)?;
+                    self.bind(skip_label);
+                    Ok(())
+                }
+            },
+            Expr::Binary(lhs, op, rhs) if expr_is_comparison(&op) => {
+                let lhs_t = self.compile_expr(*lhs, class)?;
+                let rhs_t = self.compile_expr(*rhs, class)?;
+                if lhs_t != rhs_t {
+                    return Err(super::CompileError::new(super::CompileErrorId::MismatchedTypes(lhs_t.to_string(), rhs_t.to_string()), op.line_col()));
+                }
+
+                // Offset from the `eq` variant of the `if*` instructions, negated by flipping the lowest bit
+                let cmp = match op.as_rule() {
+                    Rule::eq => 0,
+                    Rule::ne => 1,
+                    Rule::lt => 2,
+                    Rule::ge => 3,
+                    Rule::gt => 4,
+                    _ => 5, // le
+                };
+                let cmp = if jump_if { cmp } else { cmp ^ 1 };
+
+                match lhs_t.id {
+                    _ if lhs_t.is_array() || matches!(lhs_t.id, TypeId::Other(_)) => {
+                        if cmp > 1 {
+                            return Err(super::CompileError::new(super::CompileErrorId::UnsupportedOperand(op.as_str().to_string(), lhs_t.to_string()), op.line_col()));
+                        }
+                        self.pop(2);
+                        self.jump(165 + cmp, label); // if_acmp*
+                    },
+                    TypeId::I64 => {
+                        self.b.put_u8(148); // lcmp
+                        self.pop(2);
+                        self.jump(153 + cmp, label); // if*
+                    },
+                    TypeId::F32 | TypeId::F64 => {
+                        // NaN compares as 1 for `<` and `<=`, and as -1 otherwise, so that ordered comparisons are false
+                        let nan_greater = matches!(op.as_rule(), Rule::lt | Rule::le) as u8;
+                        self.b.put_u8(if lhs_t.id == TypeId::F32 { 149 } else { 151 } + nan_greater); // fcmp*, dcmp*
+                        self.pop(2);
+                        self.jump(153 + cmp, label); // if*
+                    },
+                    _ => {
+                        self.pop(2);
+                        self.jump(159 + cmp, label); // if_icmp*
+                    },
+                };
+                Ok(())
+            },
+            expr => {
+                let loc = expr.line_col();
+                let t = self.compile_expr(expr, class)?;
+                if t != Type::new(TypeId::Bool, 0) {
+                    return Err(super::CompileError::new(super::CompileErrorId::UnexpectedType(Type::new(TypeId::Bool, 0).to_string(), t.to_string()), loc));
+                }
+
+                self.pop(1);
+                self.jump(if jump_if { 154 } else { 153 }, label); // ifne, ifeq
+                Ok(())
+            },
+        }
+    }
+
+    fn compile_call_expr(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        let mut pairs = pair.into_inner();
+
+        let ident = pairs.next().unwrap();
+
+        if ident.as_str() == "print" {
+            self.b.put_u8(178); // getstatic
+            self.b.put_u16(self.cp.insert_ref(crate::compiler::constant_pool::Ref::Field, "java/lang/System".to_string(), "out".to_string(), "Ljava/io/PrintStream;".to_string()));
+            self.push_verification_type(VerificationType::Object("java/io/PrintStream".to_string()));
+            // Without the JDK's signatures, the overload is guessed from the argument types
+            let descriptor = match class.classpath.find("java/io/PrintStream") {
+                Some(print_stream) => {
+                    let candidates = print_stream.methods("println").filter(|method| !method.is_static());
+                    let descriptor = self.select_java_method(candidates, &print_stream.name, &ident, pairs.clone(), class)?;
+                    self.compile_typed_args(pairs, &descriptor, ident.line_col(), class);
+                    descriptor
+                },
+                None => self.compile_args(pairs, class),
+            };
+            self.b.put_u8(182); // invokevirtual
+            self.b.put_u16(self.cp.insert_ref(crate::compiler::constant_pool::Ref::Method, "java/io/PrintStream".to_string(), "println".to_string(), descriptor.to_string()));
+            self.pop(descriptor.args.len() + 1);
+            return Ok(Type::new(TypeId::Void, 0));
+        }
+
+        let method = match class.methods.get(ident.as_str()) {
+            Some(method) => method,
+            None => return Err(super::CompileError::new(super::CompileErrorId::SymbolNotFound(ident.as_str().to_string()), ident.line_col())),
+        };
+
+        self.compile_typed_args(pairs, &method.descriptor, ident.line_col(), class);
+        self.b.put_u8(184); // invokestatic
+        self.b.put_u16(self.cp.insert_ref(super::constant_pool::Ref::Method, class.this_class.clone(), ident.as_str().to_string(), method.descriptor.to_string()));
+        self.pop(method.descriptor.args.len());
+        self.push(&method.descriptor.return_type);
+
+        Ok(method.descriptor.return_type.clone())
+    }
+
+    /// Compiles `p.f(...)` calls, to instance methods of structures, or to their static
+    /// methods when `p` names a structure.
+    fn compile_method_call(&mut self, obj: Expr<'a>, call: Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        let mut pairs = call.into_inner();
+        let ident = pairs.next().unwrap();
+
+        // `Class.f(...)` calls a static method of an imported Java class
+        if let Some(owner) = self.imported_class(&obj, class) {
+            return self.compile_java_static_call(&owner, ident, pairs, class);
+        }
+
+        let static_owner = match &obj {
+            Expr::Value(value) if value.as_rule() == Rule::ident && self.lookup(value.as_str()).is_none() => class.structures.get(value.as_str()),
+            _ => None,
+        };
+        let (owner, instance) = match static_owner {
+            Some(structure) => (structure, false),
+            None => {
+                let obj_t = self.compile_expr(obj, class)?;
+                let structure = match &obj_t.id {
+                    TypeId::Other(name) if !obj_t.is_array() => class.structures.get(name.as_str()),
+                    _ => None,
+                };
+                match structure {
+                    Some(structure) => (structure, true),
+                    None if obj_t.is_reference() && !obj_t.is_array() => return self.compile_java_instance_call(&obj_t, ident, pairs, class),
+                    None => return Err(super::CompileError::new(super::CompileErrorId::SymbolNotFound(ident.as_str().to_string()), ident.line_col())),
+                }
             },
+        };
+
+        let method = match owner.methods.get(ident.as_str()) {
+            Some(method) if method.instance == instance => method,
+            _ => return Err(super::CompileError::new(super::CompileErrorId::SymbolNotFound(ident.as_str().to_string()), ident.line_col())),
+        };
+
+        self.compile_typed_args(pairs, &method.descriptor, ident.line_col(), class);
+        let method_ref = self.cp.insert_ref(super::constant_pool::Ref::Method, owner.name.to_string(), ident.as_str().to_string(), method.descriptor.to_string());
+        if instance {
+            self.b.put_u8(182); // invokevirtual
+            self.b.put_u16(method_ref);
+            self.pop(method.descriptor.args.len() + 1);
+        } else {
+            self.b.put_u8(184); // invokestatic
+            self.b.put_u16(method_ref);
+            self.pop(method.descriptor.args.len());
+        }
+        self.push(&method.descriptor.return_type);
+
+        Ok(method.descriptor.return_type.clone())
+    }
+
+    /// The internal name of the Java class named by `obj`, imported or from `java.lang`, unless it
+    /// is not the name of one, or a local or structure shadows it.
+    fn imported_class(&self, obj: &Expr<'a>, class: &super::ClassFile<'a>) -> Option<String> {
+        match obj {
+            Expr::Value(value) if value.as_rule() == Rule::ident => {
+                let name = value.as_str();
+                if self.lookup(name).is_some() || class.structures.contains_key(name) { return None; }
+                class.resolve_class(name)
+            },
+            _ => None,
+        }
+    }
+
+    fn compile_java_static_call(&mut self, owner: &str, ident: Pair<'a, Rule>, args: Pairs<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        let java_class = class.classpath.find(owner).unwrap();
+        let candidates = java_class.methods(ident.as_str()).filter(|method| method.is_static());
+        let descriptor = self.select_java_method(candidates, owner, &ident, args.clone(), class)?;
+
+        self.compile_typed_args(args, &descriptor, ident.line_col(), class);
+        // Static methods of interfaces are referenced as interface methods
+        let reference = if java_class.is_interface() { super::constant_pool::Ref::InterfaceMethod } else { super::constant_pool::Ref::Method };
+        self.b.put_u8(184); // invokestatic
+        self.b.put_u16(self.cp.insert_ref(reference, owner.to_string(), ident.as_str().to_string(), descriptor.to_string()));
+        self.pop(descriptor.args.len());
+        self.push(&descriptor.return_type);
+
+        Ok(descriptor.return_type)
+    }
+
+    /// Compiles a call to a method of a Java object of type `obj_t`, on top of the stack, declared
+    /// by its class or one of its supertypes.
+    fn compile_java_instance_call(&mut self, obj_t: &Type, ident: Pair<'a, Rule>, args: Pairs<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        let owner = obj_t.class_name();
+        let supertypes = class.classpath.supertypes(&owner);
+        let receiver = match supertypes.first() {
+            Some(receiver) => receiver,
+            None => return Err(super::CompileError::new(super::CompileErrorId::SymbolNotFound(owner), ident.line_col())),
+        };
+
+        let candidates = supertypes.iter().flat_map(|class| class.methods(ident.as_str())).filter(|method| !method.is_static());
+        let descriptor = self.select_java_method(candidates, &owner, &ident, args.clone(), class)?;
+
+        self.compile_typed_args(args, &descriptor, ident.line_col(), class);
+        if receiver.is_interface() {
+            self.b.put_u8(185); // invokeinterface
+            self.b.put_u16(self.cp.insert_ref(super::constant_pool::Ref::InterfaceMethod, owner, ident.as_str().to_string(), descriptor.to_string()));
+            self.b.put_u8(1 + descriptor.args.iter().map(|t| t.size()).sum::<u16>() as u8);
+            self.b.put_u8(0);
+        } else {
+            self.b.put_u8(182); // invokevirtual
+            self.b.put_u16(self.cp.insert_ref(super::constant_pool::Ref::Method, owner, ident.as_str().to_string(), descriptor.to_string()));
+        }
+        self.pop(descriptor.args.len() + 1);
+        self.push(&descriptor.return_type);
+
+        Ok(descriptor.return_type)
+    }
+
+    /// Picks the overload of the Java method `ident` of `owner` among `candidates` that the
+    /// arguments `args` can be passed to.
+    fn select_java_method<'b>(&mut self, candidates: impl Iterator<Item = &'b crate::classpath::Member>, owner: &str, ident: &Pair<'a, Rule>, args: Pairs<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Descriptor, super::CompileError> {
+        let arg_types = self.arg_types(ident.as_span().start(), args, class)?;
+        let (method, args) = (format!("{}.{}", owner, ident.as_str()), arg_types.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", "));
+        match select_overload(candidates, &arg_types, class) {
+            Overload::Found(descriptor) => Ok(descriptor),
+            Overload::NotApplicable => Err(super::CompileError::new(super::CompileErrorId::NoApplicableMethod(method, args), ident.line_col())),
+            Overload::Ambiguous => Err(super::CompileError::new(super::CompileErrorId::AmbiguousCall(method, args), ident.line_col())),
+        }
+    }
+
+    /// The types of the arguments of the call at `pos`, found by compiling them and then discarding
+    /// the code, so that they can be compiled again once the target method is known. They are kept
+    /// for when the call is compiled again as part of an enclosing call's arguments, which would
+    /// otherwise take exponential time with nested calls.
+    fn arg_types(&mut self, pos: usize, args: Pairs<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Vec<Type>, super::CompileError> {
+        if let Some(types) = self.call_arg_types.get(&pos) {
+            return Ok(types.clone());
+        }
+
+        let len = self.b.len();
+        let stack = self.stack.clone();
+        let (labels, jumps, frames) = (self.labels.len(), self.jumps.len(), self.frames.clone());
+        let (vars, next_slot, scope_slots) = (self.vars.len(), self.next_slot, self.scope_slots.len());
+        let (reachable, errs) = (self.reachable, self.errs.len());
+
+        let types: Result<Vec<_>, _> = args.map(|arg| self.compile_value(arg, class)).collect();
+
+        self.b.truncate(len);
+        self.stack = stack;
+        self.labels.truncate(labels);
+        self.jumps.truncate(jumps);
+        self.frames = frames;
+        self.vars.truncate(vars);
+        self.next_slot = next_slot;
+        self.scope_slots.truncate(scope_slots);
+        self.reachable = reachable;
+        self.errs.truncate(errs);
+
+        if let Ok(types) = &types {
+            self.call_arg_types.insert(pos, types.clone());
+        }
+        types
+    }
+
+    /// Compiles the arguments of a call to a method of type `descriptor`, checking their count and types.
+    fn compile_typed_args(&mut self, pairs: Pairs<'a, Rule>, descriptor: &Descriptor, loc: (usize, usize), class: &super::ClassFile<'a>) {
+        let mut args = vec![];
+        let expected_arg_cnt = descriptor.args.len();
+        for arg in pairs {
+            let arg_loc = arg.line_col();
+
+            if args.len() >= expected_arg_cnt {
+                self.errs.push(super::CompileError::new(super::CompileErrorId::UnexpectedArgCount(expected_arg_cnt as u16), arg_loc));
+                return;
+            }
+
+            match self.compile_value(arg, class) {
+                Ok(t) => {
+                    let arg_t = &descriptor.args[args.len()];
+                    if self.widen(&t, arg_t, class) { args.push(t); }
+                    else {
+                        self.errs.push(super::CompileError::new(super::CompileErrorId::UnexpectedArgType(arg_t.to_string(), t.to_string()), arg_loc));
+                        return;
+                    }
+                },
+                Err(e) => {
+                    self.errs.push(e);
+                    return;
+                }
+            };
+        }
+
+        if args.len() < expected_arg_cnt {
+            self.errs.push(super::CompileError::new(super::CompileErrorId::UnexpectedArgCount(expected_arg_cnt as u16), loc));
         }
     }
 
     fn compile_var_decl(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) {
         let mut pairs = pair.into_inner();
+        pairs.next(); // let
+        let mutable = match pairs.peek().unwrap().as_rule() {
+            Rule::mutable => {
+                pairs.next();
+                true
+            },
+            _ => false,
+        };
         let ident = pairs.next().unwrap().as_str();
         let t = {
             let pair = pairs.peek().unwrap();
             match pair.as_rule() {
-                Rule::primitive | Rule::object => {
-                    let t_id = pairs.next().unwrap().as_str().parse().unwrap();
-                    let is_array = match pairs.peek().unwrap().as_rule() {
-                        Rule::array => {
-                            pairs.next();
-                            true
-                        },
-                        _ => false,
-                    };
-
-                    Some(Type::new(t_id, is_array))
-                }
+                Rule::primitive | Rule::object => match class.parse_type(&mut pairs) {
+                    Ok(t) => Some(t),
+                    Err(e) => return self.errs.push(e),
+                },
                 _ => None,
             }
         };
         let v = pairs.next().unwrap();
-        let t = t.unwrap_or(self.compile_value(v, class).unwrap());
+        let v_loc = v.line_col();
+        let t = match (self.compile_value(v, class), t) {
+            (Ok(v_t), Some(t)) if self.widen(&v_t, &t, class) => t,
+            (Ok(v_t), Some(t)) => return self.errs.push(super::CompileError::new(super::CompileErrorId::UnexpectedType(t.to_string(), v_t.to_string()), v_loc)),
+            // Without a value, nothing could be stored
+            (Ok(v_t), None) if v_t.size() == 0 => return self.errs.push(super::CompileError::new(super::CompileErrorId::UnexpectedType(Type::new(TypeId::Other("java/lang/Object".to_string()), 0).to_string(), v_t.to_string()), v_loc)),
+            (Ok(v_t), None) => v_t,
+            (Err(e), _) => return self.errs.push(e),
+        };
+
+        let store_idx = self.declare(ident, t.clone(), mutable);
+        self.store(&t, store_idx);
+    }
 
-        let store_idx = (self.args.len() + self.vars.len()) as u8;
+    fn compile_assign_stmt(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) {
+        let mut pairs = pair.into_inner();
+        let target = pairs.next().unwrap();
+        let target_loc = target.line_col();
+        let op = pairs.next().unwrap();
+        let value = pairs.next().unwrap();
+        let value_loc = value.line_col();
 
-        match t.id {
-            TypeId::I8 | TypeId::I16 | TypeId::I32 | TypeId::Char | TypeId::Bool => self.b.put_u8(59 + store_idx as u8), // istore_n
-            TypeId::Other(_) => self.b.put_u8(75 + store_idx as u8), // astore_n
-            t => { unimplemented!("{t:?}") },
+        let opcode = match op.as_rule() {
+            Rule::assign => None,
+            Rule::addAssign => Some(96), // iadd
+            Rule::subAssign => Some(100), // isub
+            Rule::mulAssign => Some(104), // imul
+            Rule::divAssign => Some(108), // idiv
+            _ => Some(112), // irem
+        };
+        let value = Expr::parse(value.into_inner());
+
+        match Expr::parse(target.into_inner()) {
+            Expr::Value(ident) if ident.as_rule() == Rule::ident => {
+                let local = match self.lookup(ident.as_str()) {
+                    Some(local) if local.mutable => local.clone(),
+                    Some(_) => return self.errs.push(super::CompileError::new(super::CompileErrorId::ImmutableAssign(ident.as_str().to_string()), ident.line_col())),
+                    None => return self.errs.push(super::CompileError::new(super::CompileErrorId::SymbolNotFound(ident.as_str().to_string()), ident.line_col())),
+                };
+
+                if let (TypeId::I32, false, Some(opcode @ (96 | 100))) = (&local.t.id, local.t.is_array(), opcode) {
+                    let delta = value.const_int().map(|n| if opcode == 100 { -n } else { n });
+                    if let Some(Ok(delta)) = delta.map(i16::try_from) {
+                        return self.iinc(local.idx, delta);
+                    }
+                }
+
+                if opcode.is_some() { self.load(&local.t, local.idx); }
+                if let Err(e) = self.compile_assign_value(&local.t, opcode, op, value, value_loc, class) {
+                    return self.errs.push(e);
+                }
+                self.store(&local.t, local.idx);
+            },
+            Expr::Postfix(obj, field) if field.as_rule() == Rule::field && self.imported_class(&obj, class).is_some() => {
+                let owner = self.imported_class(&obj, class).unwrap();
+                let (member, t) = match self.resolve_static_field(&owner, &field, class) {
+                    Ok(res) => res,
+                    Err(e) => return self.errs.push(e),
+                };
+                if member.is_final() {
+                    return self.errs.push(super::CompileError::new(super::CompileErrorId::ImmutableAssign(format!("{}.{}", owner, member.name)), field.line_col()));
+                }
+                let field_ref = self.cp.insert_ref(super::constant_pool::Ref::Field, owner.to_string(), member.name, t.to_string());
+
+                if opcode.is_some() {
+                    self.b.put_u8(178); // getstatic
+                    self.b.put_u16(field_ref);
+                    self.push(&t);
+                }
+                if let Err(e) = self.compile_assign_value(&t, opcode, op, value, value_loc, class) {
+                    return self.errs.push(e);
+                }
+                self.b.put_u8(179); // putstatic
+                self.b.put_u16(field_ref);
+                self.pop(1);
+            },
+            Expr::Postfix(obj, field) if field.as_rule() == Rule::field => {
+                let (owner, name, t) = match self.compile_expr(*obj, class).and_then(|obj_t| self.resolve_field(&obj_t, &field, class)) {
+                    Ok(res) => res,
+                    Err(e) => return self.errs.push(e),
+                };
+                let field_ref = self.cp.insert_ref(super::constant_pool::Ref::Field, owner, name, t.to_string());
+
+                if opcode.is_some() {
+                    self.b.put_u8(89); // dup
+                    self.push_verification_type(self.stack.last().unwrap().clone());
+                    self.b.put_u8(180); // getfield
+                    self.b.put_u16(field_ref);
+                    self.pop(1);
+                    self.push(&t);
+                }
+                if let Err(e) = self.compile_assign_value(&t, opcode, op, value, value_loc, class) {
+                    return self.errs.push(e);
+                }
+                self.b.put_u8(181); // putfield
+                self.b.put_u16(field_ref);
+                self.pop(2);
+            },
+            Expr::Postfix(array, index) if index.as_rule() == Rule::index => {
+                let t = match self.compile_index(*array, index, class) {
+                    Ok(t) => t,
+                    Err(e) => return self.errs.push(e),
+                };
+
+                if opcode.is_some() {
+                    self.b.put_u8(92); // dup2
+                    let (array_t, index_t) = (self.stack[self.stack.len() - 2].clone(), self.stack[self.stack.len() - 1].clone());
+                    self.push_verification_type(array_t);
+                    self.push_verification_type(index_t);
+                    self.b.put_u8(46 + array_opcode_offset(&t)); // *aload
+                    self.pop(2);
+                    self.push(&t);
+                }
+                if let Err(e) = self.compile_assign_value(&t, opcode, op, value, value_loc, class) {
+                    return self.errs.push(e);
+                }
+                self.b.put_u8(79 + array_opcode_offset(&t)); // *astore
+                self.pop(3);
+            },
+            _ => self.errs.push(super::CompileError::new(super::CompileErrorId::InvalidAssignTarget, target_loc)),
+        }
+    }
+
+    /// Compiles the right-hand side of an assignment to a `t`, applying the compound operator
+    /// `opcode` to the current value, which must then already be on the stack.
+    fn compile_assign_value(&mut self, t: &Type, opcode: Option<u8>, op: Pair<'a, Rule>, value: Expr<'a>, value_loc: (usize, usize), class: &super::ClassFile<'a>) -> Result<(), super::CompileError> {
+        if t.is_string() && opcode == Some(96) { // iadd
+            self.new_string_builder(true);
+            let value_t = self.compile_expr(value, class)?;
+            self.append(&value_t, value_loc)?;
+            self.builder_to_string();
+            return Ok(());
+        }
+
+        let value_t = self.compile_expr(value, class)?;
+        if !self.widen(&value_t, t, class) {
+            return Err(super::CompileError::new(super::CompileErrorId::UnexpectedType(t.to_string(), value_t.to_string()), value_loc));
+        }
+        if let Some(opcode) = opcode {
+            self.compile_arithmetic(opcode, op, t.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Compiles a chain of `+`, which turns into a concatenation from the first string operand on,
+    /// as in Java.
+    fn compile_sum(&mut self, lhs: Expr<'a>, op: Pair<'a, Rule>, rhs: Expr<'a>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        let mut operands = vec![(op, rhs)];
+        let mut first = lhs;
+        while let Expr::Binary(lhs, op, rhs) = first {
+            if op.as_rule() != Rule::add {
+                first = Expr::Binary(lhs, op, rhs);
+                break;
+            }
+            operands.push((op, *rhs));
+            first = *lhs;
+        }
+
+        let mut t = self.compile_expr(first, class)?;
+        let mut builder = false;
+        for (op, operand) in operands.into_iter().rev() {
+            if !builder && t.is_string() {
+                self.new_string_builder(true);
+                builder = true;
+            }
+            let rhs_t = self.compile_expr(operand, class)?;
+
+            if builder {
+                self.append(&rhs_t, op.line_col())?;
+            } else if rhs_t.is_string() {
+                // The left-hand side is not a string, and is converted under the right-hand side
+                let value_of = match value_of_descriptor(&t) {
+                    Some(descriptor) => format!("({})Ljava/lang/String;", descriptor),
+                    None => return Err(super::CompileError::new(super::CompileErrorId::UnsupportedOperand(op.as_str().to_string(), t.to_string()), op.line_col())),
+                };
+                if t.size() == 2 {
+                    self.b.put_u8(91); // dup_x2
+                    self.push(&rhs_t);
+                    self.b.put_u8(87); // pop
+                    self.pop(1);
+                } else {
+                    self.b.put_u8(95); // swap
+                }
+                self.b.put_u8(184); // invokestatic
+                self.b.put_u16(self.cp.insert_ref(super::constant_pool::Ref::Method, "java/lang/String".to_string(), "valueOf".to_string(), value_of));
+                self.b.put_u8(95); // swap
+                self.b.put_u8(182); // invokevirtual
+                self.b.put_u16(self.cp.insert_ref(super::constant_pool::Ref::Method, "java/lang/String".to_string(), "concat".to_string(), "(Ljava/lang/String;)Ljava/lang/String;".to_string()));
+                self.pop(2);
+                t = rhs_t;
+                self.push(&t);
+            } else {
+                if t != rhs_t {
+                    return Err(super::CompileError::new(super::CompileErrorId::MismatchedTypes(t.to_string(), rhs_t.to_string()), op.line_col()));
+                }
+                t = self.compile_arithmetic(96, op, t)?; // iadd
+            }
+        }
+
+        if builder { self.builder_to_string(); }
+        Ok(t)
+    }
+
+    fn compile_str_lit(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        let t = Type::new(TypeId::Other("java/lang/String".to_string()), 0);
+        let loc = pair.line_col();
+        let parts = pair.into_inner().collect::<Vec<_>>();
+
+        // Plain strings are constants, others are built from their parts
+        if parts.iter().all(|part| part.as_rule() == Rule::strText) {
+            let text = parts.iter().map(|part| part.as_str()).collect::<String>();
+            self.compile_str_text(&text, loc)?;
+            return Ok(t);
+        }
+
+        self.new_string_builder(false);
+        for part in parts {
+            let loc = part.line_col();
+            let part_t = match part.as_rule() {
+                Rule::strText => {
+                    self.compile_str_text(part.as_str(), loc)?;
+                    t.clone()
+                },
+                _ => self.compile_value(part.into_inner().next().unwrap(), class)?,
+            };
+            self.append(&part_t, loc)?;
+        }
+        self.builder_to_string();
+
+        Ok(t)
+    }
+
+    /// Pushes the constant string whose source text is `text`.
+    fn compile_str_text(&mut self, text: &str, loc: (usize, usize)) -> Result<(), super::CompileError> {
+        let value = match super::expr::unescape(text) {
+            Some(value) => value,
+            None => return Err(super::CompileError::new(super::CompileErrorId::InvalidEscape(text.to_string()), loc)),
+        };
+        let len = super::constant_pool::modified_utf8(&value).len();
+        if len > u16::MAX as usize {
+            return Err(super::CompileError::new(super::CompileErrorId::StringTooLong(len), loc));
+        }
+
+        let idx = self.cp.insert_string(value);
+        self.ldc(idx);
+        self.push(&Type::new(TypeId::Other("java/lang/String".to_string()), 0));
+        Ok(())
+    }
+
+    /// Creates a `StringBuilder`, holding the string on top of the stack if `from_string` is set.
+    fn new_string_builder(&mut self, from_string: bool) {
+        let offset = self.b.len() as u16;
+        self.b.put_u8(187); // new
+        self.b.put_u16(self.cp.insert_class("java/lang/StringBuilder".to_string()));
+        self.push_verification_type(VerificationType::Uninitialized(offset));
+        if from_string {
+            // The builder goes under the string
+            self.b.put_u8(90); // dup_x1
+            self.b.put_u8(95); // swap
+        } else {
+            self.b.put_u8(89); // dup
+        }
+        self.push_verification_type(VerificationType::Uninitialized(offset));
+
+        let descriptor = if from_string { "(Ljava/lang/String;)V" } else { "()V" };
+        self.b.put_u8(183); // invokespecial
+        self.b.put_u16(self.cp.insert_ref(super::constant_pool::Ref::Method, "java/lang/StringBuilder".to_string(), "<init>".to_string(), descriptor.to_string()));
+        self.pop(if from_string { 3 } else { 2 });
+        self.push_verification_type(VerificationType::Object("java/lang/StringBuilder".to_string()));
+    }
+
+    /// Appends the value of type `t` on top of the stack to the `StringBuilder` under it.
+    fn append(&mut self, t: &Type, loc: (usize, usize)) -> Result<(), super::CompileError> {
+        let descriptor = match value_of_descriptor(t) {
+            Some(descriptor) => descriptor,
+            None => return Err(super::CompileError::new(super::CompileErrorId::UnsupportedOperand("+".to_string(), t.to_string()), loc)),
+        };
+        self.b.put_u8(182); // invokevirtual
+        self.b.put_u16(self.cp.insert_ref(super::constant_pool::Ref::Method, "java/lang/StringBuilder".to_string(), "append".to_string(), format!("({})Ljava/lang/StringBuilder;", descriptor)));
+        self.pop(1);
+        Ok(())
+    }
+
+    fn builder_to_string(&mut self) {
+        self.b.put_u8(182); // invokevirtual
+        self.b.put_u16(self.cp.insert_ref(super::constant_pool::Ref::Method, "java/lang/StringBuilder".to_string(), "toString".to_string(), "()Ljava/lang/String;".to_string()));
+        self.pop(1);
+        self.push(&Type::new(TypeId::Other("java/lang/String".to_string()), 0));
+    }
+
+    /// Finds the field accessed on an object of type `obj_t`, returning its owner, name and type.
+    fn resolve_field(&mut self, obj_t: &Type, field: &Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<(String, String, Type), super::CompileError> {
+        let ident = field.clone().into_inner().next().unwrap();
+
+        let member = match &obj_t.id {
+            TypeId::Other(name) if !obj_t.is_array() => class.structures.get(name.as_str())
+                .and_then(|structure| structure.members.get(ident.as_str())),
+            _ => None,
+        };
+        match member {
+            Some(member) => Ok((obj_t.class_name(), ident.as_str().to_string(), member.t.clone())),
+            None => Err(super::CompileError::new(super::CompileErrorId::UnknownField(obj_t.to_string(), ident.as_str().to_string()), ident.line_col())),
+        }
+    }
+
+    /// Finds the static field accessed on the Java class `owner`, returning it with its type.
+    fn resolve_static_field(&mut self, owner: &str, field: &Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<(crate::classpath::Member, Type), super::CompileError> {
+        let ident = field.clone().into_inner().next().unwrap();
+
+        // Fields may be inherited, such as the constants of interfaces
+        let member = class.classpath.supertypes(owner).iter()
+            .find_map(|class| class.field(ident.as_str()).cloned())
+            .filter(|member| member.is_public() && member.is_static());
+        match member.and_then(|member| Some((Type::parse_descriptor(&member.descriptor)?.0, member))) {
+            Some((t, member)) => Ok((member, t)),
+            None => Err(super::CompileError::new(super::CompileErrorId::UnknownField(owner.to_string(), ident.as_str().to_string()), ident.line_col())),
+        }
+    }
+
+    fn compile_field(&mut self, obj: Expr<'a>, field: Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        if let Some(owner) = self.imported_class(&obj, class) {
+            let (member, t) = self.resolve_static_field(&owner, &field, class)?;
+            match (&member.constant_value, member.is_final()) {
+                // Like in Java, compile-time constants are copied rather than read
+                (Some(value), true) => self.compile_constant(value, &t),
+                _ => {
+                    self.b.put_u8(178); // getstatic
+                    self.b.put_u16(self.cp.insert_ref(super::constant_pool::Ref::Field, owner.to_string(), member.name, t.to_string()));
+                    self.push(&t);
+                },
+            }
+            return Ok(t);
+        }
+
+        let obj_t = self.compile_expr(obj, class)?;
+        if obj_t.is_array() && field.clone().into_inner().next().unwrap().as_str() == "len" {
+            let t = Type::new(TypeId::I32, 0);
+            self.b.put_u8(190); // arraylength
+            self.pop(1);
+            self.push(&t);
+            return Ok(t);
+        }
+
+        let (owner, name, t) = self.resolve_field(&obj_t, &field, class)?;
+
+        self.b.put_u8(180); // getfield
+        self.b.put_u16(self.cp.insert_ref(super::constant_pool::Ref::Field, owner, name, t.to_string()));
+        self.pop(1);
+        self.push(&t);
+
+        Ok(t)
+    }
+
+    /// Compiles an array and an index into it, returning the type of the element.
+    fn compile_index(&mut self, array: Expr<'a>, index: Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        let loc = array.line_col();
+        let array_t = self.compile_expr(array, class)?;
+        if !array_t.is_array() {
+            return Err(super::CompileError::new(super::CompileErrorId::UnsupportedOperand("[]".to_string(), array_t.to_string()), loc));
+        }
+
+        self.compile_array_index(index.into_inner().next().unwrap(), class)?;
+        Ok(array_t.element())
+    }
+
+    /// Compiles an array index or length, which must widen to an `i32`.
+    fn compile_array_index(&mut self, value: Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<(), super::CompileError> {
+        let loc = value.line_col();
+        let int_t = Type::new(TypeId::I32, 0);
+        let t = self.compile_value(value, class)?;
+        if !self.widen(&t, &int_t, class) {
+            return Err(super::CompileError::new(super::CompileErrorId::UnexpectedType(int_t.to_string(), t.to_string()), loc));
+        }
+        Ok(())
+    }
+
+    /// Emits the allocation of an array of `t` elements, whose length is on top of the stack.
+    fn new_array(&mut self, t: &Type) {
+        let atype = match t.id {
+            _ if t.is_array() => None,
+            TypeId::Bool => Some(4),
+            TypeId::Char => Some(5),
+            TypeId::F32 => Some(6),
+            TypeId::F64 => Some(7),
+            TypeId::I8 => Some(8),
+            TypeId::I16 => Some(9),
+            TypeId::I32 => Some(10),
+            TypeId::I64 => Some(11),
+            _ => None,
+        };
+        match atype {
+            Some(atype) => {
+                self.b.put_u8(188); // newarray
+                self.b.put_u8(atype);
+            },
+            None => {
+                self.b.put_u8(189); // anewarray
+                self.b.put_u16(self.cp.insert_class(t.class_name()));
+            },
+        }
+        self.pop(1);
+        self.push(&t.array_of());
+    }
+
+    fn compile_new_array(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        let mut pairs = pair.into_inner();
+        pairs.next(); // new
+        let id = class.parse_type_id(&pairs.next().unwrap())?;
+
+        let mut lengths = 0;
+        while pairs.peek().is_some_and(|pair| pair.as_rule() != Rule::array) {
+            self.compile_array_index(pairs.next().unwrap(), class)?;
+            lengths += 1;
+        }
+        let t = Type::new(id, lengths + pairs.count() as u8);
+
+        if lengths == 1 {
+            self.new_array(&t.element());
+        } else {
+            self.b.put_u8(197); // multianewarray
+            self.b.put_u16(self.cp.insert_class(t.class_name()));
+            self.b.put_u8(lengths);
+            self.pop(lengths as usize);
+            self.push(&t);
+        }
+
+        Ok(t)
+    }
+
+    /// Compiles `[a, b, ...]`, whose elements have the type of the first one, or widen to it.
+    fn compile_array_lit(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        let mut values = pair.into_inner();
+        let len = values.len();
+        let int_t = Type::new(TypeId::I32, 0);
+
+        // The first element gives the type of the array, so it is evaluated before the allocation,
+        // into a variable that cannot be named from the source
+        let first = values.next().unwrap();
+        let first_loc = first.line_col();
+        let t = self.compile_value(first, class)?;
+        if t.size() == 0 {
+            return Err(super::CompileError::new(super::CompileErrorId::UnexpectedType(Type::new(TypeId::Other("java/lang/Object".to_string()), 0).to_string(), t.to_string()), first_loc));
+        }
+        self.push_scope();
+        let first_idx = self.declare("[]", t.clone(), false);
+        self.store(&t, first_idx);
+
+        self.compile_int(len as i64, &int_t);
+        self.new_array(&t);
+        let array_t = t.array_of();
+
+        for i in 0..len {
+            self.b.put_u8(89); // dup
+            self.push(&array_t);
+            self.compile_int(i as i64, &int_t);
+            if i == 0 {
+                self.load(&t, first_idx);
+            } else {
+                let value = values.next().unwrap();
+                let loc = value.line_col();
+                let value_t = self.compile_value(value, class)?;
+                if !self.widen(&value_t, &t, class) {
+                    return Err(super::CompileError::new(super::CompileErrorId::UnexpectedType(t.to_string(), value_t.to_string()), loc));
+                }
+            }
+            self.b.put_u8(79 + array_opcode_offset(&t)); // *astore
+            self.pop(3);
+        }
+        self.pop_scope();
+
+        Ok(array_t)
+    }
+
+    fn compile_struct_lit(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        let loc = pair.line_col();
+        let mut pairs = pair.into_inner();
+        let ident = pairs.next().unwrap();
+        let structure = match class.structures.get(ident.as_str()) {
+            Some(structure) => structure,
+            None => return Err(super::CompileError::new(super::CompileErrorId::SymbolNotFound(ident.as_str().to_string()), ident.line_col())),
         };
 
-        self.vars.insert(ident, (t, store_idx));
+        let mut inits = HashMap::new();
+        for init in pairs {
+            let text = init.as_str();
+            let mut pairs = init.into_inner();
+            let field = pairs.next().unwrap();
+            if !structure.members.contains_key(field.as_str()) {
+                return Err(super::CompileError::new(super::CompileErrorId::UnknownField(ident.as_str().to_string(), field.as_str().to_string()), field.line_col()));
+            }
+            if inits.contains_key(field.as_str()) {
+                return Err(super::CompileError::new(super::CompileErrorId::DuplicateField(field.as_str().to_string()), field.line_col()));
+            }
+            inits.insert(field.as_str(), (text, pairs.next().unwrap()));
+        }
+
+        let members = structure.ordered_members();
+        if let Some((name, _)) = members.iter().find(|(name, _)| !inits.contains_key(name)) {
+            return Err(super::CompileError::new(super::CompileErrorId::MissingField(name.to_string()), loc));
+        }
+
+        // Initializers are evaluated in source order, into variables that cannot be named from the
+        // source, then passed to the constructor in declaration order
+        let mut order = inits.iter().map(|(name, (_, value))| (value.as_span().start(), *name)).collect::<Vec<_>>();
+        order.sort();
+        self.push_scope();
+        let mut slots = HashMap::new();
+        for (_, name) in order {
+            let (text, value) = inits.remove(name).unwrap();
+            let member = &structure.members[name];
+            let value_loc = value.line_col();
+            let t = self.compile_value(value, class)?;
+            if !self.widen(&t, &member.t, class) {
+                return Err(super::CompileError::new(super::CompileErrorId::UnexpectedType(member.t.to_string(), t.to_string()), value_loc));
+            }
+            let idx = self.declare(text, member.t.clone(), false);
+            self.store(&member.t, idx);
+            slots.insert(name, idx);
+        }
+
+        let offset = self.b.len() as u16;
+        self.b.put_u8(187); // new
+        self.b.put_u16(self.cp.insert_class(structure.name.to_string()));
+        self.b.put_u8(89); // dup
+        self.push_verification_type(VerificationType::Uninitialized(offset));
+        self.push_verification_type(VerificationType::Uninitialized(offset));
+
+        for (name, member) in &members {
+            self.load(&member.t, slots[name]);
+        }
+        self.pop_scope();
+
+        self.b.put_u8(183); // invokespecial
+        self.b.put_u16(self.cp.insert_ref(super::constant_pool::Ref::Method, structure.name.to_string(), "<init>".to_string(), structure.constructor_descriptor()));
+        self.pop(members.len() + 2);
+
+        let t = Type::new(TypeId::Other(structure.name.to_string()), 0);
+        self.push(&t);
+        Ok(t)
+    }
+
+    /// Compiles `new C(...)`, creating an instance of the imported Java class `C`.
+    fn compile_new_object(&mut self, pair: Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        let mut pairs = pair.into_inner();
+        pairs.next(); // new
+        let ident = pairs.next().unwrap();
+        let java_class = match class.resolve_class(ident.as_str()).and_then(|name| class.classpath.find(&name)) {
+            Some(java_class) => java_class,
+            None => return Err(super::CompileError::new(super::CompileErrorId::SymbolNotFound(ident.as_str().to_string()), ident.line_col())),
+        };
+        if java_class.is_interface() || java_class.is_abstract() {
+            return Err(super::CompileError::new(super::CompileErrorId::AbstractInstantiation(java_class.name.to_string()), ident.line_col()));
+        }
+
+        let descriptor = self.select_java_method(java_class.methods("<init>"), &java_class.name, &ident, pairs.clone(), class)?;
+
+        let offset = self.b.len() as u16;
+        self.b.put_u8(187); // new
+        self.b.put_u16(self.cp.insert_class(java_class.name.to_string()));
+        self.b.put_u8(89); // dup
+        self.push_verification_type(VerificationType::Uninitialized(offset));
+        self.push_verification_type(VerificationType::Uninitialized(offset));
+
+        self.compile_typed_args(pairs, &descriptor, ident.line_col(), class);
+        self.b.put_u8(183); // invokespecial
+        self.b.put_u16(self.cp.insert_ref(super::constant_pool::Ref::Method, java_class.name.to_string(), "<init>".to_string(), descriptor.to_string()));
+        self.pop(descriptor.args.len() + 2);
+
+        let t = Type::new(TypeId::Other(java_class.name.to_string()), 0);
+        self.push(&t);
+        Ok(t)
     }
 
     pub fn compile_args(&mut self, pairs: Pairs<'a, Rule>, class: &super::ClassFile<'a>) -> Descriptor {
-        let mut descriptor = Descriptor::new(Vec::new(), Type::new(super::t::TypeId::Void, false));
+        let mut descriptor = Descriptor::new(Vec::new(), Type::new(super::t::TypeId::Void, 0));
         for arg in pairs {
             match self.compile_value(arg, class) {
                 Ok(t) => descriptor.args.push(t),
@@ -150,121 +1445,452 @@ impl<'a> MethodCompiler<'a> {
         Ok(match value.as_rule() {
             Rule::ident => {
                 let arg_v = value.as_str();
-                let (t, idx) =
-                    if let Some(v) = self.args.get(arg_v) { v }
-                    else if let Some(v) = self.vars.get(arg_v) { v }
-                    else {
-                        return Err(super::CompileError::new(super::CompileErrorId::SymbolNotFound(arg_v.to_string()), value.line_col()));
-                    };
-                match t.id {
-                    TypeId::I8 | TypeId::I16 | TypeId::I32 | TypeId::Char | TypeId::Bool
-                        => self.b.put_u8(26 + *idx), // iload_n
-                    TypeId::Other(_) => self.b.put_u8(42 + *idx), // aload_n
-                    _ => {},
+                let (t, idx) = match self.lookup(arg_v) {
+                    Some(local) => (local.t.clone(), local.idx),
+                    None => return Err(super::CompileError::new(super::CompileErrorId::SymbolNotFound(arg_v.to_string()), value.line_col())),
                 };
+                self.load(&t, idx);
 
-                t.clone()
+                t
             },
-            Rule::callExpr => {
-                let mut pairs = value.into_inner();
-                let ident = pairs.next().unwrap();
-                let method = class.methods.get(ident.as_str()).unwrap();
-
-                let mut args = vec![];
-                let expected_arg_cnt = method.descriptor.args.len();
-                for arg in pairs {
-                    let arg_loc = arg.line_col();
-
-                    if args.len() >= expected_arg_cnt {
-                        self.errs.push(super::CompileError::new(super::CompileErrorId::UnexpectedArgCount(expected_arg_cnt as u16), arg_loc));
-                        break;
-                    }
-
-                    match self.compile_value(arg, &class) {
-                        Ok(t) => {
-                            let arg_t = &method.descriptor.args[args.len()];
-                            if t == *arg_t { args.push(t); }
-                            else {
-                                self.errs.push(super::CompileError::new(super::CompileErrorId::UnexpectedArgType(arg_t.to_string(), t.to_string()), arg_loc));
-                                break;
-                            }
-                        },
-                        Err(e) => {
-                            self.errs.push(e);
-                            break;
-                        }
-                    };
+            Rule::callExpr => self.compile_call_expr(value, class)?,
+            Rule::structLit => self.compile_struct_lit(value, class)?,
+            Rule::newObject => self.compile_new_object(value, class)?,
+            Rule::newArray => self.compile_new_array(value, class)?,
+            Rule::arrayLit => self.compile_array_lit(value, class)?,
+            Rule::numLit => self.compile_num_lit(value, false)?,
+            Rule::strLit => self.compile_str_lit(value, class)?,
+            Rule::charLit => {
+                let t = Type::new(TypeId::Char, 0);
+                // Chars are UTF-16 code units, which cannot hold characters outside the BMP
+                match super::expr::unescape_utf16(value.clone().into_inner().next().unwrap().as_str()).as_slice() {
+                    [unit] => self.compile_int(*unit as i64, &t),
+                    _ => return Err(super::CompileError::new(super::CompileErrorId::LiteralOutOfRange(value.as_str().to_string(), t.to_string()), value.line_col())),
                 }
-
-                self.b.put_u8(184); // invokestatic
-                self.b.put_u16(self.cp.insert_ref(super::constant_pool::Ref::Method, class.this_class.clone(), ident.as_str().to_string(), method.descriptor.to_string()));
-
-                method.descriptor.return_type.clone()
+                t
             },
-            r => {
-                match r {
-                    Rule::numLit => {
-                        self.b.put_u8(16); // bipush
-                        self.b.put_u8(value.as_str().parse().unwrap());
-                        Type::new(TypeId::I32, false)
-                    },
-                    Rule::strLit => {
-                        self.b.put_u8(18); // ldc
-                        self.b.put_u8(self.cp.insert_string(value.into_inner().next().unwrap().as_str().to_string()) as u8);
-                        Type::new(TypeId::Other("String".to_string()), false)
-                    },
-                    Rule::charLit => {
-                        self.b.put_u16(16); // bipush
-                        self.b.put_u8(value.as_str().chars().skip(1).next().unwrap() as u32 as u8);
-                        Type::new(TypeId::Char, false)
-                    }
+            Rule::expr => self.compile_expr(Expr::parse(value.into_inner()), class)?,
+            _ => {
+                let t = match value.as_rule() {
+
                     Rule::boolLit => {
-                        self.b.put_u16(16); // bipush
                         self.b.put_u8(match value.as_str() {
-                            "true" => 1,
-                            _ => 0,
+                            "true" => 4, // iconst_1
+                            _ => 3, // iconst_0
                         });
-                        Type::new(TypeId::Bool, false)
+                        Type::new(TypeId::Bool, 0)
                     },
-                    _ => { unimplemented!("{r:?}") }
-                }
+                    r => { unimplemented!("{r:?}") }
+                };
+                self.push(&t);
+                t
             }
         })
     }
+
+    /// Compiles an integer literal, negated when it is the operand of a unary `-`.
+    fn compile_num_lit(&mut self, pair: Pair<'a, Rule>, negate: bool) -> Result<Type, super::CompileError> {
+        if matches!(super::expr::num_lit_type(&pair), TypeId::F32 | TypeId::F64) {
+            return self.compile_float_lit(pair, negate);
+        }
+
+        let (value, id) = super::expr::int_lit(&pair);
+        let t = Type::new(id, 0);
+        let value = value.map(|n| if negate { -n } else { n });
+
+        let (min, max) = match t.id {
+            TypeId::I8 => (i8::MIN as i128, i8::MAX as i128),
+            TypeId::I16 => (i16::MIN as i128, i16::MAX as i128),
+            TypeId::I32 => (i32::MIN as i128, i32::MAX as i128),
+            _ => (i64::MIN as i128, i64::MAX as i128),
+        };
+        let value = match value {
+            Some(n) if (min..=max).contains(&n) => n as i64,
+            _ => {
+                let lit = if negate { format!("-{}", pair.as_str()) } else { pair.as_str().to_string() };
+                return Err(super::CompileError::new(super::CompileErrorId::LiteralOutOfRange(lit, t.to_string()), pair.line_col()));
+            },
+        };
+
+        self.compile_int(value, &t);
+        Ok(t)
+    }
+
+    /// Pushes the constant `value` of the integer type `t`, with the shortest instruction.
+    fn compile_int(&mut self, value: i64, t: &Type) {
+        if t.id == TypeId::I64 {
+            match value {
+                0 | 1 => self.b.put_u8(9 + value as u8), // lconst_n
+                _ => {
+                    let idx = self.cp.insert_long(value);
+                    self.b.put_u8(20); // ldc2_w
+                    self.b.put_u16(idx);
+                },
+            }
+        } else {
+            match value {
+                -1..=5 => self.b.put_u8((3 + value) as u8), // iconst_n
+                -128..=127 => {
+                    self.b.put_u8(16); // bipush
+                    self.b.put_i8(value as i8);
+                },
+                -32768..=32767 => {
+                    self.b.put_u8(17); // sipush
+                    self.b.put_i16(value as i16);
+                },
+                _ => {
+                    let idx = self.cp.insert_integer(value as i32);
+                    self.ldc(idx);
+                },
+            }
+        }
+        self.push(t);
+    }
+
+    fn compile_float_lit(&mut self, pair: Pair<'a, Rule>, negate: bool) -> Result<Type, super::CompileError> {
+        let t = Type::new(super::expr::num_lit_type(&pair), 0);
+        let value = super::expr::float_lit(&pair);
+        let value = if negate { -value } else { value };
+
+        if value.is_infinite() || (t.id == TypeId::F32 && (value as f32).is_infinite()) {
+            let lit = if negate { format!("-{}", pair.as_str()) } else { pair.as_str().to_string() };
+            return Err(super::CompileError::new(super::CompileErrorId::LiteralOutOfRange(lit, t.to_string()), pair.line_col()));
+        }
+
+        self.compile_float(value, &t);
+        Ok(t)
+    }
+
+    fn compile_float(&mut self, value: f64, t: &Type) {
+        // Comparing bits keeps -0.0 away from the `*const_0` instructions
+        if t.id == TypeId::F32 {
+            let value = value as f32;
+            match [0.0f32, 1.0, 2.0].iter().position(|n| n.to_bits() == value.to_bits()) {
+                Some(n) => self.b.put_u8(11 + n as u8), // fconst_n
+                None => {
+                    let idx = self.cp.insert_float(value);
+                    self.ldc(idx);
+                },
+            }
+        } else {
+            match [0.0f64, 1.0].iter().position(|n| n.to_bits() == value.to_bits()) {
+                Some(n) => self.b.put_u8(14 + n as u8), // dconst_n
+                None => {
+                    let idx = self.cp.insert_double(value);
+                    self.b.put_u8(20); // ldc2_w
+                    self.b.put_u16(idx);
+                },
+            }
+        }
+        self.push(t);
+    }
+
+    /// Pushes the value of a constant field of type `t`.
+    fn compile_constant(&mut self, value: &crate::classpath::ConstantValue, t: &Type) {
+        use crate::classpath::ConstantValue;
+
+        match value {
+            ConstantValue::Integer(value) => self.compile_int(*value as i64, t),
+            ConstantValue::Long(value) => self.compile_int(*value, t),
+            ConstantValue::Float(value) => self.compile_float(*value as f64, t),
+            ConstantValue::Double(value) => self.compile_float(*value, t),
+            ConstantValue::String(value) => {
+                let idx = self.cp.insert_string(value.to_string());
+                self.ldc(idx);
+                self.push(t);
+            },
+        }
+    }
+
+    /// Emits a `ldc` of the single-word constant at `idx`, or `ldc_w` past the first 255 entries.
+    fn ldc(&mut self, idx: u16) {
+        match u8::try_from(idx) {
+            Ok(idx) => {
+                self.b.put_u8(18); // ldc
+                self.b.put_u8(idx);
+            },
+            Err(_) => {
+                self.b.put_u8(19); // ldc_w
+                self.b.put_u16(idx);
+            },
+        }
+    }
+
+    fn compile_expr(&mut self, expr: Expr<'a>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        if expr.is_condition() {
+            let false_label = self.new_label();
+            let end_label = self.new_label();
+            self.compile_cond(expr, false, false_label, class)?;
+
+            let t = Type::new(TypeId::Bool, 0);
+            self.b.put_u8(4); // iconst_1
+            self.push(&t);
+            self.jump(167, end_label); // goto
+            self.bind(false_label);
+            self.b.put_u8(3); // iconst_0
+            self.push(&t);
+            self.bind(end_label);
+
+            return Ok(t);
+        }
+
+        match expr {
+            Expr::Value(value) => self.compile_value(value, class),
+            Expr::Unary(op, rhs) if op.as_rule() == Rule::neg && matches!(&*rhs, Expr::Value(value) if value.as_rule() == Rule::numLit) => match *rhs {
+                Expr::Value(value) => self.compile_num_lit(value, true),
+                _ => unreachable!(),
+            },
+            Expr::Unary(op, rhs) => {
+                let t = self.compile_expr(*rhs, class)?;
+                let opcode = match op.as_rule() {
+                    Rule::neg => 116, // ineg
+                    r => unreachable!("{r:?}"),
+                };
+                self.compile_arithmetic(opcode, op, t)
+            },
+            Expr::Postfix(lhs, op) => match op.as_rule() {
+                Rule::field => self.compile_field(*lhs, op, class),
+                Rule::methodCall => self.compile_method_call(*lhs, op, class),
+                Rule::index => {
+                    let t = self.compile_index(*lhs, op, class)?;
+                    self.b.put_u8(46 + array_opcode_offset(&t)); // *aload
+                    self.pop(2);
+                    self.push(&t);
+                    Ok(t)
+                },
+                Rule::cast => self.compile_cast(*lhs, op, class),
+                r => unreachable!("{r:?}"),
+            },
+            Expr::Binary(lhs, op, rhs) if op.as_rule() == Rule::add => self.compile_sum(*lhs, op, *rhs, class),
+            Expr::Binary(lhs, op, rhs) => {
+                let lhs_t = self.compile_expr(*lhs, class)?;
+                let rhs_t = self.compile_expr(*rhs, class)?;
+                if lhs_t != rhs_t {
+                    return Err(super::CompileError::new(super::CompileErrorId::MismatchedTypes(lhs_t.to_string(), rhs_t.to_string()), op.line_col()));
+                }
+
+                let opcode = match op.as_rule() {
+                    Rule::add => 96, // iadd
+                    Rule::sub => 100, // isub
+                    Rule::mul => 104, // imul
+                    Rule::div => 108, // idiv
+                    Rule::rem => 112, // irem
+                    r => unreachable!("{r:?}"),
+                };
+                self.compile_arithmetic(opcode, op, lhs_t)
+            },
+        }
+    }
+
+    fn compile_cast(&mut self, expr: Expr<'a>, cast: Pair<'a, Rule>, class: &super::ClassFile<'a>) -> Result<Type, super::CompileError> {
+        let loc = cast.line_col();
+        let mut pairs = cast.into_inner();
+        let t = class.parse_type(&mut pairs)?;
+
+        let from = self.compile_expr(expr, class)?;
+        if from == t { return Ok(t); }
+
+        // Numeric types convert between each other, and booleans to integers
+        let numeric = |t: &Type| !t.is_array() && matches!(t.id, TypeId::I8 | TypeId::I16 | TypeId::I32 | TypeId::I64 | TypeId::F32 | TypeId::F64 | TypeId::Char);
+        if !numeric(&t) || !(numeric(&from) || (from == Type::new(TypeId::Bool, 0) && !matches!(t.id, TypeId::F32 | TypeId::F64))) {
+            return Err(super::CompileError::new(super::CompileErrorId::InvalidCast(from.to_string(), t.to_string()), loc));
+        }
+
+        self.convert(&from, &t);
+        Ok(t)
+    }
+
+    /// Converts the value of type `from` on top of the stack to `to`, returning whether `from` is
+    /// a subtype of `to` or widens to it.
+    fn widen(&mut self, from: &Type, to: &Type, class: &super::ClassFile<'a>) -> bool {
+        if class.is_subtype(from, to) { return true; }
+        if !from.widens_to(to) { return false; }
+
+        self.convert(from, to);
+        true
+    }
+
+    /// Emits the instructions converting the primitive on top of the stack from `from` to `to`.
+    fn convert(&mut self, from: &Type, to: &Type) {
+        use TypeId::*;
+
+        // Index of the computational type, in the `i`, `l`, `f`, `d` order of the `x2y` instructions
+        let kind = |t: &TypeId| match t {
+            I64 => 1,
+            F32 => 2,
+            F64 => 3,
+            _ => 0,
+        };
+        let (from_kind, to_kind) = (kind(&from.id), kind(&to.id));
+        if from_kind != to_kind {
+            // `i2l` up to `d2f`, skipping the conversion to the source's own kind
+            let to_idx = if to_kind > from_kind { to_kind - 1 } else { to_kind };
+            self.b.put_u8(133 + from_kind * 3 + to_idx);
+        }
+
+        // Ints are then truncated to the range of the smaller types, unless they already fit
+        let fits = match (&from.id, &to.id) {
+            (I8 | Bool, I16) => true,
+            (Bool, I8 | Char) => true,
+            (f, t) => f == t,
+        };
+        if !fits {
+            match to.id {
+                I8 => self.b.put_u8(145), // i2b
+                Char => self.b.put_u8(146), // i2c
+                I16 => self.b.put_u8(147), // i2s
+                _ => {},
+            }
+        }
+
+        self.pop(1);
+        self.push(to);
+    }
+
+    /// Emits the arithmetic instruction `opcode` (given in its `i*` form) for operands of type `t`.
+    fn compile_arithmetic(&mut self, opcode: u8, op: Pair<'a, Rule>, t: Type) -> Result<Type, super::CompileError> {
+        if t.is_array() || !matches!(t.id, TypeId::I8 | TypeId::I16 | TypeId::I32 | TypeId::I64 | TypeId::F32 | TypeId::F64) {
+            return Err(super::CompileError::new(super::CompileErrorId::UnsupportedOperand(op.as_str().to_string(), t.to_string()), op.line_col()));
+        }
+
+        self.pop(if opcode == 116 { 1 } else { 2 }); // ineg
+        match t.id {
+            TypeId::I8 => {
+                self.b.put_u8(opcode);
+                self.b.put_u8(145); // i2b
+            },
+            TypeId::I16 => {
+                self.b.put_u8(opcode);
+                self.b.put_u8(147); // i2s
+            },
+            TypeId::I32 => self.b.put_u8(opcode),
+            TypeId::I64 => self.b.put_u8(opcode + 1), // l*
+            TypeId::F32 => self.b.put_u8(opcode + 2), // f*
+            TypeId::F64 => self.b.put_u8(opcode + 3), // d*
+            _ => unreachable!(),
+        };
+        self.push(&t);
+
+        Ok(t)
+    }
+}
+
+/// Offset of the instruction handling elements of type `t` from its `int` variant, in the
+/// `*aload` and `*astore` families, ordered `i`, `l`, `f`, `d`, `a`, `b`, `c`, `s`.
+fn array_opcode_offset(t: &Type) -> u8 {
+    match t.id {
+        _ if t.is_array() => 4,
+        TypeId::I8 | TypeId::Bool => 5,
+        TypeId::Char => 6,
+        TypeId::I16 => 7,
+        _ => t.opcode_offset(),
+    }
+}
+
+/// The outcome of overload resolution.
+enum Overload {
+    Found(Descriptor),
+    NotApplicable,
+    /// Several methods apply, none of them more specific than all the others.
+    Ambiguous,
+}
+
+/// Picks the method to call with arguments of types `args` among `candidates`: like Java, the most
+/// specific of those they convert to, whose parameters convert to those of all the others.
+fn select_overload<'b>(candidates: impl Iterator<Item = &'b crate::classpath::Member>, args: &[Type], class: &super::ClassFile) -> Overload {
+    let converts_to = |from: &Type, to: &Type| class.is_subtype(from, to) || from.widens_to(to);
+    let applicable = candidates
+        .filter(|method| method.is_public())
+        .filter_map(|method| Descriptor::parse(&method.descriptor))
+        .filter(|descriptor| descriptor.args.len() == args.len() && args.iter().zip(&descriptor.args).all(|(arg, param)| converts_to(arg, param)))
+        .collect::<Vec<_>>();
+
+    if applicable.is_empty() { return Overload::NotApplicable; }
+    match applicable.iter().find(|descriptor| applicable.iter().all(|other| descriptor.args.iter().zip(&other.args).all(|(arg, param)| converts_to(arg, param)))) {
+        Some(descriptor) => Overload::Found(descriptor.clone()),
+        None => Overload::Ambiguous,
+    }
+}
+
+/// The parameter type of the `String.valueOf` and `StringBuilder.append` overloads taking a `t`.
+fn value_of_descriptor(t: &Type) -> Option<&'static str> {
+    Some(match t.id {
+        _ if t.is_string() => "Ljava/lang/String;",
+        _ if t.is_array() => "Ljava/lang/Object;",
+        TypeId::I8 | TypeId::I16 | TypeId::I32 => "I",
+        TypeId::I64 => "J",
+        TypeId::F32 => "F",
+        TypeId::F64 => "D",
+        TypeId::Char => "C",
+        TypeId::Bool => "Z",
+        TypeId::Void => return None,
+        TypeId::Other(_) => "Ljava/lang/Object;",
+    })
+}
+
+fn expr_is_comparison(op: &Pair<Rule>) -> bool {
+    matches!(op.as_rule(), Rule::eq | Rule::ne | Rule::lt | Rule::le | Rule::gt | Rule::ge)
+}
+
+/// A variable or an argument, stored in a local slot.
+#[derive(Debug, Clone)]
+pub struct Local {
+    pub t: Type,
+    pub idx: u16,
+    pub mutable: bool,
 }
 
 #[derive(Debug)]
 pub struct Method<'a> {
     pub name: &'a str,
     pub descriptor: Descriptor,
-    pub args: HashMap<&'a str, (Type, u8)>,
+    pub args: HashMap<&'a str, Local>,
     pub code: pest::iterators::Pair<'a, Rule>,
+    pub instance: bool,
 }
 
 impl<'a> Method<'a> {
-    pub fn parse(pairs: &mut pest::iterators::Pairs<'a, Rule>) -> Self {
+    /// Parses a function declared in `owner`, which takes `self` if it is an instance method.
+    pub fn parse(pairs: &mut pest::iterators::Pairs<'a, Rule>, owner: &str, class: &super::ClassFile<'a>, errs: &mut Vec<super::CompileError>) -> Self {
+        // Unknown types are reported, and replaced so that the body can still be checked
+        let mut parse_type = |pairs: &mut pest::iterators::Pairs<'a, Rule>| class.parse_type(pairs).unwrap_or_else(|e| {
+            errs.push(e);
+            Type::new(TypeId::Other("java/lang/Object".to_string()), 0)
+        });
+
         let ident = pairs.next().unwrap().as_str();
+        let instance = match pairs.peek().unwrap().as_rule() {
+            Rule::selfParam => {
+                pairs.next();
+                true
+            },
+            _ => false,
+        };
 
         let mut params = vec![];
-        let next = loop {
-            let node = pairs.next().unwrap();
-            if node.as_rule() != Rule::param { break node; }
-
-            let mut pairs = node.into_inner();
+        while pairs.peek().unwrap().as_rule() == Rule::param {
+            let mut pairs = pairs.next().unwrap().into_inner();
             let ident = pairs.next().unwrap().as_str();
-            let t = Type::new(pairs.next().unwrap().as_str().parse().unwrap(), pairs.next().is_some());
+            let t = parse_type(&mut pairs);
 
             params.push((ident, t));
-        };
+        }
 
-        let (ret_type, block) =
-            if next.as_rule() == Rule::block { (Type::new(super::t::TypeId::Void, false), next) }
-            else { (Type::new(next.as_str().parse().unwrap(), false), pairs.next().unwrap()) };
+        let ret_type =
+            if pairs.peek().unwrap().as_rule() == Rule::body { Type::new(super::t::TypeId::Void, 0) }
+            else { parse_type(pairs) };
+        let block = pairs.next().unwrap();
 
         let mut arg_map = HashMap::new();
         let mut arg_lst = vec![];
+        let mut idx = 0;
+        if instance {
+            arg_map.insert("self", Local { t: Type::new(TypeId::Other(owner.to_string()), 0), idx, mutable: false });
+            idx += 1;
+        }
         for (ident, t) in params {
-            arg_map.insert(ident, (t.clone(), arg_map.len() as u8));
+            arg_map.insert(ident, Local { t: t.clone(), idx, mutable: false });
+            idx += t.size();
             arg_lst.push(t);
         }
         
@@ -275,6 +1901,7 @@ impl<'a> Method<'a> {
             descriptor,
             args: arg_map,
             code: block,
+            instance,
         }
     }
 }
@@ -1,4 +1,6 @@
 pub mod constant_pool;
+pub mod expr;
+pub mod frame;
 pub mod method;
 pub mod structure;
 pub mod t;
@@ -9,6 +11,7 @@ pub use error::{CompileErrorId, CompileError, Result};
 use std::collections::HashMap;
 
 use crate::Rule;
+use crate::classpath::ClassPath;
 
 use bytes::BufMut;
 
@@ -33,10 +36,13 @@ pub struct ClassFile<'a> {
     super_class: String,
     methods: HashMap<String, method::Method<'a>>,
     structures: HashMap<String, structure::Structure<'a>>,
+    /// Internal names of the imported Java classes, by simple name.
+    imports: HashMap<String, String>,
+    classpath: ClassPath,
 }
 
 impl<'a> ClassFile<'a> {
-    pub fn new(magic: u32, version: Version, access_flags: u16, this_class: String, super_class: String) -> Self {
+    pub fn new(magic: u32, version: Version, access_flags: u16, this_class: String, super_class: String, classpath: ClassPath) -> Self {
         Self {
             magic,
             version,
@@ -45,29 +51,71 @@ impl<'a> ClassFile<'a> {
             super_class,
             methods: HashMap::new(),
             structures: HashMap::new(),
+            imports: HashMap::new(),
+            classpath,
         }
     }
 
-    pub fn compile(&mut self, ast: &mut pest::iterators::Pairs<'a, Rule>) -> Result<Vec<u8>> {
+    /// Compiles the module, returning the name and content of every class file it produces.
+    pub fn compile(&mut self, ast: &mut pest::iterators::Pairs<'a, Rule>) -> Result<Vec<(String, Vec<u8>)>> {
         let mut errs = vec![];
 
         let mut cp = constant_pool::ConstantPool::new();
 
+        // Imports and structures are known before resolving the types that name them
+        for node in ast.clone() {
+            match node.as_rule() {
+                Rule::structDecl => {
+                    let name = node.into_inner().next().unwrap().as_str();
+                    self.structures.insert(name.to_string(), structure::Structure::new(name));
+                },
+                Rule::importDecl => {
+                    let path = node.into_inner().next().unwrap();
+                    let name = path.as_str().replace('.', "/");
+                    match self.classpath.find(&name) {
+                        Some(_) => {
+                            let simple = name.rsplit('/').next().unwrap().to_string();
+                            self.imports.insert(simple, name);
+                        },
+                        None => errs.push(CompileError::new(CompileErrorId::SymbolNotFound(path.as_str().to_string()), path.line_col())),
+                    }
+                },
+                _ => {
+                },
+            }
+        }
+
+        let mut impls = vec![];
         for node in ast {
             match node.as_rule() {
                 Rule::functionDecl => {
-                    let method = self::method::Method::parse(&mut node.into_inner());
+                    let method = self::method::Method::parse(&mut node.into_inner(), &self.this_class, self, &mut errs);
                     self.methods.insert(method.name.to_string(), method);
                 },
                 Rule::structDecl => {
-                    let structure = self::structure::Structure::parse(&mut node.into_inner());
+                    let structure = self::structure::Structure::parse(&mut node.into_inner(), self, &mut errs);
                     self.structures.insert(structure.name.to_string(), structure);
-                }
+                },
+                Rule::implDecl => impls.push(node),
                 _ => {
                 },
             }
         }
 
+        // Structures may be declared after their methods
+        for node in impls {
+            let mut pairs = node.into_inner();
+            let ident = pairs.next().unwrap();
+            if !self.structures.contains_key(ident.as_str()) {
+                errs.push(CompileError::new(CompileErrorId::SymbolNotFound(ident.as_str().to_string()), ident.line_col()));
+                continue;
+            }
+
+            let methods = pairs.map(|node| method::Method::parse(&mut node.into_inner(), ident.as_str(), self, &mut errs)).collect::<Vec<_>>();
+            let structure = self.structures.get_mut(ident.as_str()).unwrap();
+            structure.methods.extend(methods.into_iter().map(|method| (method.name.to_string(), method)));
+        }
+
         let mut body = bytes::BytesMut::new();
         body.put_u16(self.access_flags);
         body.put_u16(cp.insert_class(self.this_class.to_string()));
@@ -78,14 +126,77 @@ impl<'a> ClassFile<'a> {
         body.put_u16(0);
 
         body.put_u16(self.methods.len() as u16);
-        for (_, method) in &self.methods {
+        for method in self.methods.values() {
             let mut ctx = method::MethodCompiler::new(&mut cp, method);
-            body.put_slice(&ctx.compile(method.code.clone(), &self));
+            body.put_slice(&ctx.compile(method.code.clone(), self));
             errs.append(&mut ctx.errs);
         }
 
         body.put_u16(0);
 
+        let mut classes = vec![(self.this_class.to_string(), self.serialize(&cp, &body))];
+        for structure in self.structures.values() {
+            let mut cp = constant_pool::ConstantPool::new();
+            match structure.compile(&mut cp, self) {
+                Ok(body) => classes.push((structure.name.to_string(), self.serialize(&cp, &body))),
+                Err(mut e) => errs.append(&mut e),
+            }
+        }
+
+        if errs.is_empty() { Ok(classes) }
+        else { Err(errs) }
+    }
+
+    /// Resolves the name of a class written in a type, simple or qualified with its package, to
+    /// its internal name.
+    pub fn resolve_class(&self, name: &str) -> Option<String> {
+        if name.contains('/') || self.structures.contains_key(name) { return Some(name.to_string()); }
+        if let Some(name) = self.imports.get(name) { return Some(name.to_string()); }
+
+        // Like in Java, the classes of `java.lang` need no import
+        let name = if name.contains('.') { name.replace('.', "/") } else { format!("java/lang/{}", name) };
+        self.classpath.find(&name).map(|_| name)
+    }
+
+    /// The type named by a `primitive` or `object` pair, resolving the class of the latter.
+    pub fn parse_type_id(&self, pair: &pest::iterators::Pair<'a, Rule>) -> std::result::Result<t::TypeId, CompileError> {
+        match pair.as_str().parse().unwrap() {
+            t::TypeId::Other(name) => match self.resolve_class(&name) {
+                Some(name) => Ok(t::TypeId::Other(name)),
+                None => Err(CompileError::new(CompileErrorId::SymbolNotFound(pair.as_str().to_string()), pair.line_col())),
+            },
+            id => Ok(id),
+        }
+    }
+
+    /// Parses a type, resolving the class it names.
+    pub fn parse_type(&self, pairs: &mut pest::iterators::Pairs<'a, Rule>) -> std::result::Result<t::Type, CompileError> {
+        let pair = pairs.peek().unwrap();
+        let mut t = t::Type::parse(pairs);
+        t.id = self.parse_type_id(&pair)?;
+        Ok(t)
+    }
+
+    /// Whether `from` is the same reference type as `to` or one of its subtypes, so that its
+    /// values can be used as `to` values as they are.
+    pub fn is_subtype(&self, from: &t::Type, to: &t::Type) -> bool {
+        if !from.is_reference() || !to.is_reference() { return from == to; }
+        if to.id == t::TypeId::Other("java/lang/Object".to_string()) && !to.is_array() { return true; }
+
+        match (from.is_array(), to.is_array()) {
+            (true, true) => self.is_subtype(&from.element(), &to.element()),
+            (true, false) => matches!(&to.id, t::TypeId::Other(name) if name == "java/lang/Cloneable" || name == "java/io/Serializable"),
+            (false, true) => false,
+            (false, false) => match (&from.id, &to.id) {
+                // Structures only extend `Object`
+                (t::TypeId::Other(from), t::TypeId::Other(to)) if self.structures.contains_key(from.as_str()) => from == to,
+                (t::TypeId::Other(from), t::TypeId::Other(to)) => self.classpath.is_subclass(from, to),
+                _ => false,
+            },
+        }
+    }
+
+    fn serialize(&self, cp: &constant_pool::ConstantPool, body: &[u8]) -> Vec<u8> {
         let mut buf = bytes::BytesMut::new();
 
         buf.put_u32(self.magic);
@@ -93,9 +204,8 @@ impl<'a> ClassFile<'a> {
         buf.put_u16(self.version.major);
 
         buf.put_slice(&cp.serialize());
-        buf.put_slice(&body);
+        buf.put_slice(body);
 
-        if errs.len() == 0 { Ok(buf.to_vec()) }
-        else { Err(errs) }
+        buf.to_vec()
     }
 }
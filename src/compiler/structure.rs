@@ -2,26 +2,64 @@ use std::collections::HashMap;
 
 use crate::Rule;
 
+use super::constant_pool::{ConstantPool, Ref};
 use super::t::Type;
-use super::method::Method;
+use super::method::{Method, MethodCompiler};
+
+use bytes::{BufMut, BytesMut};
+
+/// A field of a structure. Members without `pub` stay visible to the rest of the
+/// module, so they are package-private rather than private on the JVM.
+#[derive(Debug)]
+pub struct Member {
+    pub t: Type,
+    pub idx: usize,
+    pub public: bool,
+}
 
 #[derive(Debug)]
 pub struct Structure<'a> {
     pub name: &'a str,
-    pub members: HashMap<String, (Type, u8)>,
+    pub members: HashMap<String, Member>,
     pub methods: HashMap<String, Method<'a>>,
 }
 
 impl<'a> Structure<'a> {
-    pub fn parse(pairs: &mut pest::iterators::Pairs<'a, Rule>) -> Self {
+    /// Declares the structure `name`, whose members are not parsed yet.
+    pub fn new(name: &'a str) -> Self {
+        Self {
+            name,
+            members: HashMap::new(),
+            methods: HashMap::new(),
+        }
+    }
+
+    pub fn parse(pairs: &mut pest::iterators::Pairs<'a, Rule>, class: &super::ClassFile<'a>, errs: &mut Vec<super::CompileError>) -> Self {
         let ident = pairs.next().unwrap().as_str();
 
         let mut members = HashMap::new();
         for member in pairs {
             let mut pairs = member.into_inner();
-            let ident = pairs.next().unwrap().as_str();
-            let t = Type::new(pairs.next().unwrap().as_str().parse().unwrap(), pairs.next().is_some());
-            members.insert(ident.to_string(), (t, members.len() as u8));
+            let public = match pairs.peek().unwrap().as_rule() {
+                Rule::public => {
+                    pairs.next();
+                    true
+                },
+                _ => false,
+            };
+            let ident = pairs.next().unwrap();
+            if members.contains_key(ident.as_str()) {
+                errs.push(super::CompileError::new(super::CompileErrorId::DuplicateMember(ident.as_str().to_string()), ident.line_col()));
+                continue;
+            }
+            let t = match class.parse_type(&mut pairs) {
+                Ok(t) => t,
+                Err(e) => {
+                    errs.push(e);
+                    continue;
+                },
+            };
+            members.insert(ident.as_str().to_string(), Member { t, idx: members.len(), public });
         }
 
         Structure {
@@ -30,4 +68,140 @@ impl<'a> Structure<'a> {
             methods: HashMap::new(),
         }
     }
+
+    /// The members, in declaration order.
+    pub fn ordered_members(&self) -> Vec<(&str, &Member)> {
+        let mut members = self.members.iter().map(|(name, member)| (name.as_str(), member)).collect::<Vec<_>>();
+        members.sort_by_key(|(_, member)| member.idx);
+        members
+    }
+
+    /// Descriptor of the constructor, which takes every member in declaration order.
+    pub fn constructor_descriptor(&self) -> String {
+        let mut buf = String::from("(");
+        for (_, member) in self.ordered_members() {
+            buf.push_str(&member.t.to_string());
+        }
+        buf.push_str(")V");
+        buf
+    }
+
+    /// Compiles the body of the structure's class file, starting from its access flags.
+    pub fn compile(&self, cp: &mut ConstantPool, class: &super::ClassFile<'a>) -> super::Result<Vec<u8>> {
+        let mut errs = vec![];
+
+        let mut body = BytesMut::new();
+        body.put_u16(1 | 32);
+        body.put_u16(cp.insert_class(self.name.to_string()));
+        body.put_u16(cp.insert_class("java/lang/Object".to_string()));
+
+        body.put_u16(0);
+
+        let members = self.ordered_members();
+        body.put_u16(members.len() as u16);
+        for (name, member) in &members {
+            body.put_u16(if member.public { 1 } else { 0 });
+            body.put_u16(cp.insert_utf8(name.to_string()));
+            body.put_u16(cp.insert_utf8(member.t.to_string()));
+            body.put_u16(0);
+        }
+
+        // Methods of the `impl` blocks take the place of the getters they would clash with
+        let getters = members.iter()
+            .filter(|(name, member)| !self.methods.get(getter_name(name).as_str())
+                .is_some_and(|method| method.descriptor.to_string() == getter_descriptor(member)))
+            .collect::<Vec<_>>();
+
+        body.put_u16((getters.len() + self.methods.len()) as u16 + 1);
+        body.put_slice(&self.compile_constructor(cp));
+        for (name, member) in getters {
+            body.put_slice(&self.compile_getter(cp, name, member));
+        }
+        for method in self.methods.values() {
+            let mut ctx = MethodCompiler::new(cp, method);
+            body.put_slice(&ctx.compile(method.code.clone(), class));
+            errs.append(&mut ctx.errs);
+        }
+
+        body.put_u16(0);
+
+        if errs.is_empty() { Ok(body.to_vec()) }
+        else { Err(errs) }
+    }
+
+    fn compile_constructor(&self, cp: &mut ConstantPool) -> Vec<u8> {
+        let mut code = BytesMut::new();
+        code.put_u8(42); // aload_0
+        code.put_u8(183); // invokespecial
+        code.put_u16(cp.insert_ref(Ref::Method, "java/lang/Object".to_string(), "<init>".to_string(), "()V".to_string()));
+
+        let mut max_stack = 1;
+        let mut idx = 1;
+        for (name, member) in self.ordered_members() {
+            let size = member.t.size();
+
+            code.put_u8(42); // aload_0
+            if idx <= 255 {
+                code.put_u8(21 + member.t.opcode_offset()); // *load
+                code.put_u8(idx as u8);
+            } else {
+                code.put_u8(196); // wide
+                code.put_u8(21 + member.t.opcode_offset()); // *load
+                code.put_u16(idx);
+            }
+            code.put_u8(181); // putfield
+            code.put_u16(cp.insert_ref(Ref::Field, self.name.to_string(), name.to_string(), member.t.to_string()));
+
+            idx += size;
+            max_stack = max_stack.max(1 + size);
+        }
+        code.put_u8(177); // return
+
+        compile_method(cp, 1, "<init>", &self.constructor_descriptor(), max_stack, idx, &code)
+    }
+
+    fn compile_getter(&self, cp: &mut ConstantPool, name: &str, member: &Member) -> Vec<u8> {
+        let mut code = BytesMut::new();
+        code.put_u8(42); // aload_0
+        code.put_u8(180); // getfield
+        code.put_u16(cp.insert_ref(Ref::Field, self.name.to_string(), name.to_string(), member.t.to_string()));
+        code.put_u8(172 + member.t.opcode_offset()); // *return
+
+        compile_method(cp, 1, &getter_name(name), &getter_descriptor(member), 2, 1, &code)
+    }
+}
+
+/// The name of the getter of the member `name`, such as `getX` for `x`.
+fn getter_name(name: &str) -> String {
+    let mut getter = String::from("get");
+    let mut chars = name.chars();
+    getter.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+    getter.push_str(chars.as_str());
+    getter
+}
+
+fn getter_descriptor(member: &Member) -> String {
+    format!("(){}", member.t.to_string())
+}
+
+fn compile_method(cp: &mut ConstantPool, access_flags: u16, name: &str, descriptor: &str, max_stack: u16, max_locals: u16, code: &[u8]) -> Vec<u8> {
+    let mut body = BytesMut::new();
+    body.put_u16(max_stack);
+    body.put_u16(max_locals);
+    body.put_u32(code.len() as u32);
+    body.put_slice(code);
+    body.put_u16(0);
+    body.put_u16(0);
+
+    let mut res = BytesMut::new();
+    res.put_u16(access_flags);
+    res.put_u16(cp.insert_utf8(name.to_string()));
+    res.put_u16(cp.insert_utf8(descriptor.to_string()));
+
+    res.put_u16(1);
+    res.put_u16(cp.insert_utf8("Code".to_string()));
+    res.put_u32(body.len() as u32);
+    res.put_slice(&body);
+
+    res.to_vec()
 }
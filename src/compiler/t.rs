@@ -1,8 +1,12 @@
+use crate::Rule;
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum TypeId {
     I8, I16, I32, I64,
+    F32, F64,
     Char, Bool,
     Void,
+    /// A class, by internal name.
     Other(String),
 }
 
@@ -17,8 +21,12 @@ impl std::str::FromStr for TypeId {
             "i16" => I16,
             "i32" => I32,
             "i64" => I64,
+            "f32" => F32,
+            "f64" => F64,
             "char" => Char,
             "bool" => Bool,
+            "Object" => Other("java/lang/Object".to_string()),
+            "String" => Other("java/lang/String".to_string()),
             s => Other(s.to_string()),
         })
     }
@@ -27,12 +35,120 @@ impl std::str::FromStr for TypeId {
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Type {
     pub id: TypeId,
-    array: bool,
+    /// Number of array dimensions, `0` for a plain value.
+    dims: u8,
 }
 
 impl Type {
-    pub fn new(id: TypeId, array: bool) -> Self {
-        Self { id, array }
+    pub fn new(id: TypeId, dims: u8) -> Self {
+        Self { id, dims }
+    }
+
+    /// Parses a `type`, made of a `primitive` or an `object` followed by its `array` suffixes.
+    pub fn parse(pairs: &mut pest::iterators::Pairs<Rule>) -> Self {
+        let id = pairs.next().unwrap().as_str().parse().unwrap();
+        let mut dims = 0;
+        while pairs.peek().is_some_and(|pair| pair.as_rule() == Rule::array) {
+            pairs.next();
+            dims += 1;
+        }
+        Self::new(id, dims)
+    }
+
+    /// Parses the field descriptor at the start of `descriptor`, returning it with the rest.
+    pub fn parse_descriptor(descriptor: &str) -> Option<(Self, &str)> {
+        use TypeId::*;
+
+        let rest = descriptor.trim_start_matches('[');
+        let dims = (descriptor.len() - rest.len()) as u8;
+        let (id, rest) = match rest.chars().next()? {
+            'B' => (I8, &rest[1..]),
+            'S' => (I16, &rest[1..]),
+            'I' => (I32, &rest[1..]),
+            'J' => (I64, &rest[1..]),
+            'F' => (F32, &rest[1..]),
+            'D' => (F64, &rest[1..]),
+            'C' => (Char, &rest[1..]),
+            'Z' => (Bool, &rest[1..]),
+            'V' => (Void, &rest[1..]),
+            'L' => {
+                let (name, rest) = rest[1..].split_once(';')?;
+                (Other(name.to_string()), rest)
+            },
+            _ => return None,
+        };
+        Some((Self::new(id, dims), rest))
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.dims > 0
+    }
+
+    /// Number of local variable slots (or stack words) taken by a value of this type.
+    pub fn size(&self) -> u16 {
+        match self.id {
+            _ if self.is_array() => 1,
+            TypeId::I64 | TypeId::F64 => 2,
+            TypeId::Void => 0,
+            _ => 1,
+        }
+    }
+
+    /// Offset of the instruction handling this type from its `int` variant, in the
+    /// `*load`, `*store` and `*return` families, ordered `i`, `l`, `f`, `d`, `a`.
+    pub fn opcode_offset(&self) -> u8 {
+        match self.id {
+            _ if self.is_array() => 4,
+            TypeId::I64 => 1,
+            TypeId::F32 => 2,
+            TypeId::F64 => 3,
+            TypeId::Other(_) => 4,
+            _ => 0,
+        }
+    }
+
+    /// The type of the elements of an array.
+    pub fn element(&self) -> Self {
+        Self::new(self.id.clone(), self.dims - 1)
+    }
+
+    /// The type of an array of this type.
+    pub fn array_of(&self) -> Self {
+        Self::new(self.id.clone(), self.dims + 1)
+    }
+
+    /// Whether values of this type are references to objects or arrays.
+    pub fn is_reference(&self) -> bool {
+        self.is_array() || matches!(self.id, TypeId::Other(_))
+    }
+
+    pub fn is_string(&self) -> bool {
+        !self.is_array() && self.class_name() == "java/lang/String"
+    }
+
+    /// Whether a value of this type converts to `other` without loss of magnitude, as in Java's
+    /// widening primitive conversions.
+    pub fn widens_to(&self, other: &Type) -> bool {
+        use TypeId::*;
+
+        if self.is_array() || other.is_array() { return false; }
+        match self.id {
+            I8 => matches!(other.id, I16 | I32 | I64 | F32 | F64),
+            I16 | Char => matches!(other.id, I32 | I64 | F32 | F64),
+            I32 => matches!(other.id, I64 | F32 | F64),
+            I64 => matches!(other.id, F32 | F64),
+            F32 => other.id == F64,
+            _ => false,
+        }
+    }
+
+    /// The name of the type as a class reference, as found in `CONSTANT_Class` entries.
+    pub fn class_name(&self) -> String {
+        let descriptor = self.to_string();
+        match descriptor.strip_prefix('L').and_then(|s| s.strip_suffix(';')) {
+            Some(name) if !self.is_array() => name.to_string(),
+            _ => descriptor,
+        }
     }
 }
 
@@ -41,21 +157,23 @@ impl ToString for Type {
         use TypeId::*;
 
         let mut buf = String::new();
-        if self.array { buf.push('[') }
-        buf.push_str(match &self.id {
-            I8 => "B",
-            I16 => "S",
-            I32 => "I",
-            I64 => "J",
-            Char => "C",
-            Bool => "Z",
-            Void => "V",
-            Other(s) => match s.as_str() {
-                "Object" => "Ljava/lang/Object;",
-                "String" => "Ljava/lang/String;",
-                s => s,
+        for _ in 0..self.dims { buf.push('[') }
+        match &self.id {
+            I8 => buf.push('B'),
+            I16 => buf.push('S'),
+            I32 => buf.push('I'),
+            I64 => buf.push('J'),
+            F32 => buf.push('F'),
+            F64 => buf.push('D'),
+            Char => buf.push('C'),
+            Bool => buf.push('Z'),
+            Void => buf.push('V'),
+            Other(s) => {
+                buf.push('L');
+                buf.push_str(s);
+                buf.push(';');
             },
-        });
+        };
         buf
     }
 }
@@ -70,6 +188,22 @@ impl Descriptor {
     pub fn new(args: Vec<Type>, return_type: Type) -> Self {
         Self { args, return_type }
     }
+
+    /// Parses a method descriptor, such as `(ILjava/lang/String;)V`.
+    pub fn parse(descriptor: &str) -> Option<Self> {
+        let mut rest = descriptor.strip_prefix('(')?;
+        let mut args = vec![];
+        while !rest.starts_with(')') {
+            let (t, next) = Type::parse_descriptor(rest)?;
+            args.push(t);
+            rest = next;
+        }
+
+        match Type::parse_descriptor(&rest[1..])? {
+            (return_type, "") => Some(Self::new(args, return_type)),
+            _ => None,
+        }
+    }
 }
 
 impl ToString for Descriptor {
@@ -1,3 +1,4 @@
+mod classpath;
 mod compiler;
 
 use pest::Parser;
@@ -8,7 +9,22 @@ use pest_derive::Parser;
 struct LeaParser;
 
 fn main() {
-    let file = std::env::args().skip(1).next().unwrap_or("main.lea".to_string());
+    let mut file = None;
+    let mut classpath = vec![];
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--classpath" | "-cp" => if let Some(paths) = args.next() {
+                classpath.extend(std::env::split_paths(&paths));
+            },
+            _ => file = Some(arg),
+        }
+    }
+    let file = file.unwrap_or("main.lea".to_string());
+    let classpath = match classpath::ClassPath::new(&classpath) {
+        Ok(classpath) => classpath,
+        Err(e) => return println!("error: {e}"),
+    };
     println!("{:?}", file);
     let src = std::fs::read_to_string(&file).unwrap();
 
@@ -23,11 +39,14 @@ fn main() {
                 compiler::Version::new(0, 52),
                 1 | 32,
                 this.to_string(), "java/lang/Object".to_string(),
+                classpath,
             );
 
             let now = std::time::Instant::now();
             match class.compile(&mut pairs) {
-                Ok(data) => std::fs::write(format!("{}.class", this), data).unwrap(),
+                Ok(classes) => for (name, data) in classes {
+                    std::fs::write(format!("{}.class", name), data).unwrap();
+                },
                 Err(errs) => for err in errs {
                     err.print(&file, &src);
                 }
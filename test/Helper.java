@@ -0,0 +1,17 @@
+public class Helper {
+  public static final String NAME = "helper";
+  public static final long START = System.nanoTime();
+  public static int counter;
+
+  public static int twice(int x) {
+    return x * 2;
+  }
+
+  public static String describe(Object o) {
+    return "object";
+  }
+
+  public static String describe(CharSequence s) {
+    return "chars " + s;
+  }
+}
@@ -1,3 +1,4 @@
+import static org.junit.Assert.assertArrayEquals;
 import static org.junit.Assert.assertEquals;
 
 import org.junit.Test;
@@ -9,4 +10,211 @@ public class Main {
     assertEquals(5, Tests.num_return());
     assertEquals(false, Tests.bool_return());
   }
+
+  @Test public void keyword_identifiers() {
+    assertEquals(7, Tests.keyword_idents());
+  }
+
+  @Test public void arithmetic() {
+    assertEquals(7 + 3 * 2 - (7 - 3) / 3 % 2, Tests.arithmetic(7, 3));
+    assertEquals(-10, Tests.negate(5));
+    assertEquals(42, Tests.precedence());
+  }
+
+  @Test public void if_statement() {
+    assertEquals(4, Tests.max(4, 2));
+    assertEquals(7, Tests.max(3, 7));
+    assertEquals(-1, Tests.sign(-5));
+    assertEquals(0, Tests.sign(0));
+    assertEquals(1, Tests.sign(9));
+  }
+
+  @Test public void conditions() {
+    assertEquals(true, Tests.in_range(3, 1, 5));
+    assertEquals(false, Tests.in_range(6, 1, 5));
+    assertEquals("yes", Tests.either(true, true));
+    assertEquals("yes", Tests.either(false, false));
+    assertEquals("no", Tests.either(false, true));
+    assertEquals(true, Tests.same("a", "a"));
+  }
+
+  @Test public void loops() {
+    assertEquals(3, Tests.count_down(3));
+    assertEquals(0, Tests.count_down(0));
+    assertEquals(7, Tests.first_multiple(7));
+    assertEquals("broke", Tests.loop_break());
+    assertEquals(3, Tests.labelled(5));
+    assertEquals(0, Tests.labelled(2));
+  }
+
+  @Test public void assignment() {
+    assertEquals(45, Tests.sum_to(10));
+    assertEquals(120, Tests.factorial(5));
+    assertEquals("bar", Tests.reassign());
+    assertEquals(5, Tests.keyword_prefix());
+  }
+
+  @Test public void structures() throws Exception {
+    Point p = new Point(1, 2);
+    assertEquals(1, p.getX());
+    assertEquals(2, p.getY());
+    assertEquals(1, p.x);
+    assertEquals(true, java.lang.reflect.Modifier.isPublic(Point.class.getDeclaredField("x").getModifiers()));
+    assertEquals(false, java.lang.reflect.Modifier.isPublic(Point.class.getDeclaredField("y").getModifiers()));
+
+    Named n = new Named("foo", 42L, p);
+    assertEquals("foo", n.getName());
+    assertEquals(42L, n.getId());
+    assertEquals(p, n.getOrigin());
+    assertEquals(3, new Wrapped(2).getValue());
+  }
+
+  @Test public void struct_literals() {
+    Point p = Tests.make_point(3, 4);
+    assertEquals(3, p.x);
+    assertEquals(4, p.getY());
+    assertEquals(7, Tests.point_sum(p));
+
+    Tests.move_point(p);
+    assertEquals(10, p.x);
+    assertEquals(9, p.getY());
+    assertEquals(10, Tests.origin_x(new Named("foo", 1L, p)));
+  }
+
+  @Test public void methods() {
+    Point p = new Point(1, 2);
+    assertEquals(3, p.manhattan());
+    assertEquals(9, Tests.scaled_manhattan(p, 3));
+    assertEquals(3, p.x);
+    assertEquals(0, Tests.origin_manhattan());
+    assertEquals(0, Point.origin().getY());
+  }
+
+  @Test public void locals() {
+    assertEquals(7L + 2L + 3L + 4L + 5L + 6L, Tests.many_locals(2L, 3, 7L));
+  }
+
+  @Test public void literals() {
+    assertEquals(200 - 1 + 100_000 + 0xff + 0b1010 - 32768, Tests.int_literals());
+    assertEquals(10_000_000_001L, Tests.long_literal());
+  }
+
+  @Test public void floats() {
+    assertEquals(25.0, Tests.hypot_sq(3.0, 4.0), 0.0);
+    assertEquals(1.25f, Tests.halve(2.5f), 0.0f);
+    assertEquals(1.5 - 0.25 + 1000.0 + 250.0 - 1.0, Tests.float_literals(), 0.0);
+    assertEquals(Float.floatToIntBits(1.00000017881393432617187499f), Float.floatToIntBits(Tests.precise_f32()));
+    assertEquals(true, Tests.float_less(1.0, 2.0));
+    assertEquals(false, Tests.float_less(Double.NaN, 2.0));
+    assertEquals(true, Tests.float_not_ge(Float.NaN, 2.0f));
+    assertEquals(false, Tests.float_not_ge(3.0f, 2.0f));
+  }
+
+  @Test public void casts() {
+    assertEquals(-2L + 3_000_000_000L * 3, Tests.widen((byte) -2, 1_000_000_000) + 6_000_000_000L);
+    assertEquals((byte) 300, Tests.truncate(300));
+    assertEquals('A', Tests.to_char(65L + 65536L));
+    assertEquals(-2, Tests.to_int(2.9));
+    assertEquals(2.5f, Tests.average(2, 3), 0.0f);
+  }
+
+  @Test public void widening() {
+    assertEquals(-3.0 + 'a' - 3.0, Tests.widening((byte) -3, 'a'), 0.0);
+    assertEquals(-7L, Tests.widen_return((short) -7));
+  }
+
+  @Test public void returns() {
+    assertEquals("negative", Tests.classify(-4));
+    assertEquals("zero", Tests.classify(0));
+    assertEquals("positive", Tests.classify(8));
+    assertEquals(16L, Tests.first_square_above(10L));
+    Point p = new Point(1, 2);
+    Tests.early_exit(p, true);
+    assertEquals(1, p.x);
+    Tests.early_exit(p, false);
+    assertEquals(0, p.x);
+  }
+
+  @Test public void return_expressions() {
+    assertEquals(3, Tests.add(1, 2));
+    assertEquals(6L, Tests.add_three(1, 2, 3));
+    assertEquals(5, Tests.tail_point(4));
+    assertEquals(20, Tests.tail_after_loop(5));
+    Tests.log_twice("twice");
+  }
+
+  @Test public void arrays() {
+    assertEquals(2, Tests.arg_count(new String[] { "a", "b" }));
+    assertEquals("a", Tests.first_arg(new String[] { "a", "b" }));
+    assertEquals(java.util.Arrays.toString(new long[] { 0L, 1L, 4L, 9L }), java.util.Arrays.toString(Tests.squares(4)));
+    assertEquals(1 + 20 + 12, Tests.literal_sum());
+    assertArrayEquals(new String[] { "a", "b", "c" }, Tests.names());
+    assertEquals(6, Tests.points());
+  }
+
+  @Test public void multi_dimensional_arrays() {
+    assertEquals(java.util.Arrays.deepToString(new int[][] { { 0, 1, 2 }, { 3, 4, 5 } }), java.util.Arrays.deepToString(Tests.grid(3, 2)));
+    assertEquals(13L, Tests.jagged());
+    assertArrayEquals(new String[][] { { "a", "b" }, { "c" } }, Tests.nested_literal());
+  }
+
+  @Test public void strings() {
+    assertEquals("hello Lea!", Tests.greet("Lea"));
+    assertEquals("p = (1, 2), n + 1 = 4, ok = true", Tests.describe(new Point(1, 2), 3L));
+    assertEquals("3 and 1.5xtrue", Tests.concat(2, 1.5, 'x'));
+    assertEquals("5!", Tests.number_first(5L));
+    assertEquals("0123", Tests.build(4));
+  }
+
+  @Test public void string_escapes() {
+    assertEquals("a\nb\t\"c\" \\ \u00e9\u0000 {x}", Tests.escapes());
+    assertEquals("h\u00e9llo \ud83d\ude00 \ud83d\ude00 Lea\n", Tests.unicode("Lea"));
+  }
+
+  @Test public void chars() {
+    assertEquals("a\u00e9\n'\"\u20ac\\{\ufb00", Tests.chars());
+    assertEquals(233, Tests.char_code('\u00e9'));
+    assertEquals('\uffff', Tests.high_char());
+  }
+
+  @Test public void java_static_calls() {
+    assertEquals(7L, Tests.java_max(7, -2L));
+    assertEquals(9L, Tests.java_max(3, 9L));
+    assertEquals("1.5 ff 3 45", Tests.java_statics(2.25));
+  }
+
+  @Test public void classpath() {
+    assertEquals("42 chars x object object", Tests.classpath_calls(new Point(1, 2)));
+  }
+
+  @Test public void java_objects() {
+    assertEquals("<0,1,2,>", Tests.builder(3));
+    assertEquals(3 + 2 + 1 + 1, Tests.java_objects("abc"));
+  }
+
+  @Test public void static_fields() {
+    Helper.counter = 0;
+    assertEquals("2147483647 -9223372036854775808 true 65535 helper 3 true", Tests.static_fields());
+    assertEquals(3, Helper.counter);
+  }
+
+  @Test public void java_types() {
+    java.util.ArrayList<Object> list = new java.util.ArrayList<>();
+    assertEquals(list, Tests.qualified_types(list));
+    assertEquals(1, Tests.list_size(list));
+    assertEquals(Integer.valueOf(5), Tests.boxed(5));
+    assertEquals(3, Tests.builders(3).length);
+    Holder h = Tests.holder(list);
+    assertEquals(list, h.getItems());
+    assertEquals("holder", h.getName());
+  }
+
+  @Test public void init_order() {
+    java.util.ArrayList<Object> log = new java.util.ArrayList<>();
+    Named n = Tests.init_order(log);
+    assertEquals(java.util.Arrays.asList("y", "x", "name"), log);
+    assertEquals(1, n.getOrigin().getX());
+    assertEquals(2, n.getOrigin().getY());
+    assertEquals("true", n.getName());
+  }
 }
@@ -19,7 +19,8 @@ in stdenv.mkDerivation {
   src = ./.;
 
   buildPhase = ''
-    leac tests.lea
+    javac Helper.java
+    leac --classpath . tests.lea
 
     cp ${junit} junit.jar
     cp ${hamcrest} hamcrest.jar
@@ -28,7 +29,7 @@ in stdenv.mkDerivation {
 
   installPhase = ''
     mkdir -p $out/bin
-    cp Main.class Tests.class junit.jar hamcrest.jar $out/bin
+    cp *.class junit.jar hamcrest.jar $out/bin
     echo "${jdk}/bin/java -cp \"$out/bin:$out/bin/junit.jar:$out/bin/hamcrest.jar\" org.junit.runner.JUnitCore Main" > $out/bin/lea-test
     chmod +x $out/bin/lea-test
   '';